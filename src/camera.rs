@@ -0,0 +1,361 @@
+use crate::*;
+
+/// moves the camera passed to Scene::update around, the size of that camera is what zoom 1 sees
+pub(crate) struct CameraController {
+    // the sprite to keep in view
+    target: Option<SpriteId>,
+    // zooms and moves to keep all of these in view instead, with the margin around them
+    fit: Option<(Vec<SpriteId>, f32)>,
+    // half the size of the box around the center the target can move in without the camera
+    // following, in world units
+    pub(crate) dead_zone: Vector2d,
+    // how many seconds of the target's velocity the camera leads it by
+    pub(crate) look_ahead: f32,
+    // roughly how long the camera takes to catch up, 0 snaps
+    pub(crate) smooth_time: f32,
+    // the view never leaves this, in world coords
+    pub(crate) bounds: Option<Rect>,
+    // 2 sees half as much, ignored while fitting. set through set_zoom and set_zoom_range so
+    // the range is never upside down
+    zoom: f32,
+    min_zoom: f32,
+    max_zoom: f32,
+    // trauma goes down by this much per second, the shake is trauma squared times the max
+    pub(crate) trauma_decay: f32,
+    pub(crate) max_shake_offset: Vector2d,
+    pub(crate) max_shake_angle: f32,
+    // how fast the shake wobbles
+    pub(crate) shake_frequency: f32,
+    trauma: f32,
+    // where the camera would like to be ignoring the smoothing and the look ahead
+    goal: Vector2d,
+    center: Vector2d,
+    velocity: Vector2d,
+    current_zoom: f32,
+    zoom_velocity: f32,
+    last_target: Option<Vector2d>,
+    lead: Vector2d,
+    // the first update snaps to the goal
+    is_placed: bool,
+}
+
+impl CameraController {
+    fn new() -> Self {
+        Self {
+            target: None,
+            fit: None,
+            dead_zone: Vector2d { x: 0.0, y: 0.0 },
+            look_ahead: 0.0,
+            smooth_time: 0.2,
+            bounds: None,
+            zoom: 1.0,
+            min_zoom: 0.1,
+            max_zoom: 10.0,
+            trauma_decay: 1.0,
+            max_shake_offset: Vector2d { x: 16.0, y: 16.0 },
+            max_shake_angle: 0.0,
+            shake_frequency: 15.0,
+            trauma: 0.0,
+            goal: Vector2d { x: 0.0, y: 0.0 },
+            center: Vector2d { x: 0.0, y: 0.0 },
+            velocity: Vector2d { x: 0.0, y: 0.0 },
+            current_zoom: 1.0,
+            zoom_velocity: 0.0,
+            last_target: None,
+            lead: Vector2d { x: 0.0, y: 0.0 },
+            is_placed: false,
+        }
+    }
+
+    // 0 to 1, stacks up to 1
+    pub(crate) fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    pub(crate) fn trauma(&self) -> f32 {
+        self.trauma
+    }
+
+    pub(crate) fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    // kept within the zoom range when applied
+    pub(crate) fn set_zoom(&mut self, zoom: f32) -> Result<(), &'static str> {
+        if zoom.is_nan() || zoom <= 0.0 {
+            return Err("zoom has to be above 0");
+        }
+        self.zoom = zoom;
+        Ok(())
+    }
+
+    pub(crate) fn zoom_range(&self) -> (f32, f32) {
+        (self.min_zoom, self.max_zoom)
+    }
+
+    pub(crate) fn set_zoom_range(&mut self, min: f32, max: f32) -> Result<(), &'static str> {
+        if min.is_nan() || min <= 0.0 || !max.is_finite() {
+            return Err("zoom range has to be above 0 and finite");
+        }
+        if min > max {
+            return Err("min zoom has to be at most the max zoom");
+        }
+        self.min_zoom = min;
+        self.max_zoom = max;
+        Ok(())
+    }
+}
+
+/// critically damped spring towards `target`, game programming gems 4 ch 1.10
+fn smooth_damp(
+    current: f32,
+    target: f32,
+    velocity: &mut f32,
+    smooth_time: f32,
+    delta_t: f32,
+) -> f32 {
+    if smooth_time <= 0.0 || delta_t <= 0.0 {
+        *velocity = 0.0;
+        return target;
+    }
+    let omega = 2.0 / smooth_time;
+    let x = omega * delta_t;
+    let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - target;
+    let temp = (*velocity + omega * change) * delta_t;
+    *velocity = (*velocity - omega * temp) * decay;
+    target + (change + temp) * decay
+}
+
+// smooth noise from -1 to 1, a couple of sines that don't line up is plenty for a shake
+fn wobble(time: f32, seed: f32) -> f32 {
+    ((time * 1.0 + seed).sin()
+        + (time * 2.3 + seed * 1.7).sin() * 0.5
+        + (time * 4.1 + seed * 2.9).sin() * 0.25)
+        / 1.75
+}
+
+impl Scene {
+    // makes one on first use, from then on update goes through it
+    pub(crate) fn camera_controller_mut(&mut self) -> &mut CameraController {
+        self.camera_controller
+            .get_or_insert_with(CameraController::new)
+    }
+
+    // the camera passed to update is used as is again
+    pub(crate) fn disable_camera_controller(&mut self) {
+        self.camera_controller = None;
+    }
+
+    pub(crate) fn camera_follow(&mut self, sprite: Option<&Sprite>) {
+        let controller = self.camera_controller_mut();
+        controller.target = sprite.map(|sprite| sprite.id());
+        controller.last_target = None;
+    }
+
+    /// keeps all of the sprites in view, zooming out as far as max_zoom allows, until
+    /// camera_stop_fit, `margin` is in world units
+    pub(crate) fn camera_fit(&mut self, sprites: &[&Sprite], margin: f32) {
+        let ids = sprites.iter().map(|sprite| sprite.id()).collect();
+        self.camera_controller_mut().fit = Some((ids, margin));
+    }
+
+    pub(crate) fn camera_stop_fit(&mut self) {
+        self.camera_controller_mut().fit = None;
+    }
+
+    pub(crate) fn add_camera_trauma(&mut self, amount: f32) {
+        self.camera_controller_mut().add_trauma(amount);
+    }
+
+    /// what the last update actually rendered with
+    pub(crate) fn camera(&self) -> Camera {
+        self.last_camera
+    }
+
+    /// the camera the frame gets rendered with, `cam` as is without a controller
+    pub(crate) fn update_camera(&mut self, cam: &Camera, delta_t: f32) -> Camera {
+        let mut controller = match self.camera_controller.take() {
+            Some(controller) => controller,
+            None => return *cam,
+        };
+        let (base_width, base_height) = cam.rect.edge_lengths();
+
+        // where the sprites to keep in view are, and the zoom they need
+        let mut zoom = controller.zoom;
+        let fit = controller.fit.as_ref().and_then(|(ids, margin)| {
+            let mut bound: Option<(Vector2d, Vector2d)> = None;
+            for id in ids {
+                let rect = match self.sprite_data(*id) {
+                    Ok(data) => data.rect.get_bounding_rect(),
+                    Err(_) => continue,
+                };
+                let (tl, br) = (rect.top_left(), rect.bottom_right());
+                bound = Some(match bound {
+                    None => (tl, br),
+                    Some((min, max)) => (
+                        Vector2d {
+                            x: min.x.min(tl.x),
+                            y: min.y.min(tl.y),
+                        },
+                        Vector2d {
+                            x: max.x.max(br.x),
+                            y: max.y.max(br.y),
+                        },
+                    ),
+                });
+            }
+            let (min, max) = bound?;
+            let width = max.x - min.x + margin * 2.0;
+            let height = max.y - min.y + margin * 2.0;
+            zoom = (base_width / width).min(base_height / height);
+            Some((min + max) * 0.5)
+        });
+        let zoom = zoom.clamp(controller.min_zoom, controller.max_zoom);
+
+        let target = controller
+            .target
+            .and_then(|id| self.sprite_data(id).ok())
+            .map(|data| data.rect.get_bounding_rect().center_global());
+        match (fit, target) {
+            (Some(center), _) => {
+                controller.goal = center;
+                controller.lead = Vector2d { x: 0.0, y: 0.0 };
+            }
+            (None, Some(target)) => {
+                // the goal only moves once the target pushes against the edge of the dead zone
+                let zone = controller.dead_zone;
+                let goal = &mut controller.goal;
+                goal.x = goal.x.clamp(target.x - zone.x, target.x + zone.x);
+                goal.y = goal.y.clamp(target.y - zone.y, target.y + zone.y);
+                if !controller.is_placed {
+                    controller.goal = target;
+                }
+                let lead = match controller.last_target {
+                    Some(last) if delta_t > 0.0 => {
+                        (target - last) * (controller.look_ahead / delta_t)
+                    }
+                    _ => Vector2d { x: 0.0, y: 0.0 },
+                };
+                // the lead is smoothed on its own so a jittery target doesn't shake the camera
+                controller.lead =
+                    controller.lead + (lead - controller.lead) * (delta_t * 4.0).min(1.0);
+                controller.last_target = Some(target);
+            }
+            (None, None) => {
+                if !controller.is_placed {
+                    controller.goal = cam.rect.center_global();
+                }
+            }
+        }
+
+        // the view is kept inside the bounds, or centered on them if it's bigger
+        let (width, height) = (base_width / zoom, base_height / zoom);
+        let mut goal = controller.goal + controller.lead;
+        if let Some(bounds) = controller.bounds {
+            let (tl, br) = (bounds.top_left(), bounds.bottom_right());
+            let clamp = |value: f32, min: f32, max: f32, half: f32| {
+                if max - min <= half * 2.0 {
+                    (min + max) * 0.5
+                } else {
+                    value.clamp(min + half, max - half)
+                }
+            };
+            goal.x = clamp(goal.x, tl.x, br.x, width * 0.5);
+            goal.y = clamp(goal.y, tl.y, br.y, height * 0.5);
+        }
+
+        if controller.is_placed {
+            let smooth_time = controller.smooth_time;
+            controller.center.x = smooth_damp(
+                controller.center.x,
+                goal.x,
+                &mut controller.velocity.x,
+                smooth_time,
+                delta_t,
+            );
+            controller.center.y = smooth_damp(
+                controller.center.y,
+                goal.y,
+                &mut controller.velocity.y,
+                smooth_time,
+                delta_t,
+            );
+            controller.current_zoom = smooth_damp(
+                controller.current_zoom,
+                zoom,
+                &mut controller.zoom_velocity,
+                smooth_time,
+                delta_t,
+            );
+        } else {
+            controller.center = goal;
+            controller.current_zoom = zoom;
+            controller.is_placed = true;
+        }
+
+        let (width, height) = (
+            base_width / controller.current_zoom,
+            base_height / controller.current_zoom,
+        );
+        let shake = controller.trauma * controller.trauma;
+        let time = self.time * controller.shake_frequency;
+        let offset = Vector2d {
+            x: controller.max_shake_offset.x * shake * wobble(time, 0.0),
+            y: controller.max_shake_offset.y * shake * wobble(time, 10.0),
+        };
+        let angle = controller.max_shake_angle * shake * wobble(time, 20.0);
+        controller.trauma = (controller.trauma - controller.trauma_decay * delta_t).max(0.0);
+
+        let center = controller.center + offset;
+        let mut rect = Rect::new_raw(
+            center.x - width * 0.5,
+            center.y - height * 0.5,
+            0.0,
+            width,
+            height,
+        );
+        if angle != 0.0 {
+            rect.set_rotation_with_origin(angle, rect.center_origin());
+        }
+        self.camera_controller = Some(controller);
+        Camera { rect }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smooth_damp_settles_on_target() {
+        let (mut value, mut velocity) = (0.0, 0.0);
+        let mut last_gap = f32::MAX;
+        for _ in 0..120 {
+            value = smooth_damp(value, 10.0, &mut velocity, 0.2, 1.0 / 60.0);
+            // critically damped, it closes in without going past
+            assert!(value <= 10.0);
+            assert!(10.0 - value <= last_gap);
+            last_gap = 10.0 - value;
+        }
+        assert!(last_gap < 0.01);
+    }
+
+    #[test]
+    fn smooth_damp_snaps_without_smoothing() {
+        let mut velocity = 5.0;
+        assert_eq!(smooth_damp(0.0, 10.0, &mut velocity, 0.0, 0.1), 10.0);
+        assert_eq!(velocity, 0.0);
+    }
+
+    #[test]
+    fn zoom_range_is_validated() {
+        let mut controller = CameraController::new();
+        assert!(controller.set_zoom_range(2.0, 1.0).is_err());
+        assert!(controller.set_zoom_range(0.0, 1.0).is_err());
+        assert!(controller.set_zoom_range(f32::NAN, 1.0).is_err());
+        assert!(controller.set_zoom(0.0).is_err());
+        assert!(controller.set_zoom_range(0.5, 0.5).is_ok());
+        assert_eq!(controller.zoom_range(), (0.5, 0.5));
+    }
+}
@@ -0,0 +1,160 @@
+use crate::*;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct ClipId(u32);
+
+// a region that any number of sprites can be put under, nothing of them is drawn outside of it
+pub(crate) struct Clip {
+    pub(crate) rect: Rect,
+}
+
+impl Frame {
+    // s and t go from 0 to 1 across the frame, same as Rect::sub_rect
+    pub(crate) fn sub_frame(&self, s0: f32, t0: f32, s1: f32, t1: f32) -> Self {
+        let point_at = |s: f32, t: f32| {
+            (
+                self.top_left.0
+                    + (self.top_right.0 - self.top_left.0) * s
+                    + (self.bottom_left.0 - self.top_left.0) * t,
+                self.top_left.1
+                    + (self.top_right.1 - self.top_left.1) * s
+                    + (self.bottom_left.1 - self.top_left.1) * t,
+            )
+        };
+        Self {
+            top_left: point_at(s0, t0),
+            bottom_left: point_at(s0, t1),
+            bottom_right: point_at(s1, t1),
+            top_right: point_at(s1, t0),
+        }
+    }
+}
+
+/// cuts an axis aligned quad down to an axis aligned clip, uv included, None if nothing is left.
+/// a mirrored quad, one scaled by a negative amount, stays mirrored
+pub(crate) fn clip_quad(rect: &Rect, frame: &Frame, clip: &Rect) -> Option<(Rect, Frame)> {
+    let (start, end) = (rect.top_left(), rect.bottom_right());
+    // negative when mirrored
+    let (width, height) = (end.x - start.x, end.y - start.y);
+    if width == 0.0 || height == 0.0 {
+        return None;
+    }
+
+    let clip_left = start.x.min(end.x).max(clip.top_left().x);
+    let clip_top = start.y.min(end.y).max(clip.top_left().y);
+    let clip_right = start.x.max(end.x).min(clip.bottom_right().x);
+    let clip_bottom = start.y.max(end.y).min(clip.bottom_right().y);
+    if clip_left >= clip_right || clip_top >= clip_bottom {
+        return None;
+    }
+
+    // back into the quad's own s and t, which run the other way along a mirrored axis
+    let (s_a, s_b) = (
+        (clip_left - start.x) / width,
+        (clip_right - start.x) / width,
+    );
+    let (t_a, t_b) = (
+        (clip_top - start.y) / height,
+        (clip_bottom - start.y) / height,
+    );
+    let (s0, s1) = (s_a.min(s_b), s_a.max(s_b));
+    let (t0, t1) = (t_a.min(t_b), t_a.max(t_b));
+    Some((
+        rect.sub_rect(s0, t0, s1, t1),
+        frame.sub_frame(s0, t0, s1, t1),
+    ))
+}
+
+/// the clip a sprite gets cut down to on the cpu, or the one it needs as a stencil mask
+pub(crate) fn resolve_clip<'a>(
+    clips: &'a HashMap<ClipId, Clip>,
+    data: &SpriteData,
+) -> (Option<&'a Rect>, Option<ClipId>) {
+    match data.clip.and_then(|id| Some((id, clips.get(&id)?))) {
+        None => (None, None),
+        Some((_, clip)) if clip.rect.is_axis_aligned() && data.rect.is_axis_aligned() => {
+            (Some(&clip.rect), None)
+        }
+        Some((id, _)) => (None, Some(id)),
+    }
+}
+
+impl Scene {
+    pub(crate) fn add_clip(&mut self, x: f32, y: f32, width: f32, height: f32) -> ClipId {
+        let id = ClipId(self.next_clip_id);
+        self.next_clip_id += 1;
+        self.clips.insert(
+            id,
+            Clip {
+                rect: Rect::new_raw(x, y, 0.0, width, height),
+            },
+        );
+        id
+    }
+
+    // sprites under a removed clip are simply drawn unclipped
+    pub(crate) fn remove_clip(&mut self, id: ClipId) -> Result<(), &'static str> {
+        self.clips.remove(&id).ok_or("invalid clip id")?;
+        Ok(())
+    }
+
+    // resets any rotation
+    pub(crate) fn set_clip_rect(
+        &mut self,
+        id: ClipId,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> Result<(), &'static str> {
+        let clip = self.clips.get_mut(&id).ok_or("invalid clip id")?;
+        clip.rect = Rect::new_raw(x, y, 0.0, width, height);
+        Ok(())
+    }
+
+    // rotates by rad around the center of the clip, a rotated clip has to go through the stencil
+    // buffer instead of being cut on the cpu
+    pub(crate) fn set_clip_rotation(&mut self, id: ClipId, rad: f32) -> Result<(), &'static str> {
+        let clip = self.clips.get_mut(&id).ok_or("invalid clip id")?;
+        // the diagonal still crosses the center once rotated, unlike width/height
+        let center = ((clip.rect.bottom_right() - clip.rect.top_left()) * 0.5).to_origin();
+        clip.rect.set_rotation_with_origin(rad, center);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_quad_cuts_rect_and_uv() {
+        let rect = Rect::new_raw(0.0, 0.0, 0.0, 10.0, 10.0);
+        let frame = Frame::from_uv(0.0, 0.0, 1.0, 1.0);
+        let clip = Rect::new_raw(5.0, -5.0, 0.0, 20.0, 10.0);
+        let (rect, frame) = clip_quad(&rect, &frame, &clip).unwrap();
+        assert_eq!(rect, Rect::new_raw(5.0, 0.0, 0.0, 5.0, 5.0));
+        assert_eq!(frame.top_left, (0.5, 0.0));
+        assert_eq!(frame.bottom_right, (1.0, 0.5));
+    }
+
+    #[test]
+    fn clip_quad_outside_is_none() {
+        let rect = Rect::new_raw(0.0, 0.0, 0.0, 10.0, 10.0);
+        let frame = Frame::from_uv(0.0, 0.0, 1.0, 1.0);
+        let clip = Rect::new_raw(20.0, 0.0, 0.0, 10.0, 10.0);
+        assert!(clip_quad(&rect, &frame, &clip).is_none());
+    }
+
+    #[test]
+    fn clip_quad_keeps_mirrored_quads_mirrored() {
+        // scaled by -1 along x, s runs from right to left
+        let rect = Rect::new_raw(10.0, 0.0, 0.0, -10.0, 10.0);
+        let frame = Frame::from_uv(0.0, 0.0, 1.0, 1.0);
+        let clip = Rect::new_raw(0.0, 0.0, 0.0, 5.0, 10.0);
+        let (rect, frame) = clip_quad(&rect, &frame, &clip).unwrap();
+        assert_eq!(rect, Rect::new_raw(5.0, 0.0, 0.0, -5.0, 10.0));
+        assert_eq!(frame.top_left, (0.5, 0.0));
+        assert_eq!(frame.bottom_right, (1.0, 1.0));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::*;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct DebugVertex {
+    x: f32,
+    y: f32,
+    color: Color,
+}
+
+/// untextured triangles in world coords, drawn over the main camera's pass. shapes with a
+/// thickness of 0 are filled, polygons only fill right when they're convex
+#[derive(Default)]
+pub(crate) struct DebugDraw {
+    vertices: Vec<DebugVertex>,
+}
+
+impl DebugDraw {
+    pub(crate) fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub(crate) fn vertices(&self) -> &[DebugVertex] {
+        &self.vertices
+    }
+
+    fn triangle(&mut self, a: Vector2d, b: Vector2d, c: Vector2d, color: Color) {
+        for point in [a, b, c] {
+            self.vertices.push(DebugVertex {
+                x: point.x,
+                y: point.y,
+                color,
+            });
+        }
+    }
+
+    // `cap` pushes the ends out by half the thickness, so outlines meet at square corners
+    fn segment(&mut self, a: Vector2d, b: Vector2d, color: Color, thickness: f32, cap: bool) {
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return;
+        }
+        let half = thickness.max(0.0) * 0.5;
+        let along = Vector2d {
+            x: dx / len * half,
+            y: dy / len * half,
+        };
+        let across = Vector2d {
+            x: -along.y,
+            y: along.x,
+        };
+        let (a, b) = if cap { (a - along, b + along) } else { (a, b) };
+        self.triangle(a + across, b + across, b - across, color);
+        self.triangle(a + across, b - across, a - across, color);
+    }
+
+    pub(crate) fn line(&mut self, a: Vector2d, b: Vector2d, color: Color, thickness: f32) {
+        self.segment(a, b, color, thickness, false);
+    }
+
+    pub(crate) fn poly(&mut self, points: &[Vector2d], color: Color, thickness: f32) {
+        if points.len() < 2 {
+            return;
+        }
+        if thickness <= 0.0 {
+            for pair in points[1..].windows(2) {
+                self.triangle(points[0], pair[0], pair[1], color);
+            }
+            return;
+        }
+        for (index, point) in points.iter().enumerate() {
+            let next = points[(index + 1) % points.len()];
+            self.segment(*point, next, color, thickness, true);
+        }
+    }
+
+    // follows the corners, so rotated rects come out rotated
+    pub(crate) fn rect(&mut self, rect: &Rect, color: Color, thickness: f32) {
+        let corners = [
+            rect.top_left(),
+            rect.top_right(),
+            rect.bottom_right(),
+            rect.bottom_left(),
+        ];
+        self.poly(&corners, color, thickness);
+    }
+
+    pub(crate) fn circle(&mut self, center: Vector2d, radius: f32, color: Color, thickness: f32) {
+        // roughly one segment every few units of circumference
+        let count = ((radius * PI * 2.0 / 4.0) as usize).clamp(12, 64);
+        let points: Vec<Vector2d> = (0..count)
+            .map(|index| {
+                let angle = index as f32 / count as f32 * PI * 2.0;
+                center
+                    + Vector2d {
+                        x: angle.cos() * radius,
+                        y: angle.sin() * radius,
+                    }
+            })
+            .collect();
+        self.poly(&points, color, thickness);
+    }
+}
+
+impl Scene {
+    // everything drawn with these shows up in the next update only
+    pub(crate) fn debug_line(&mut self, a: Vector2d, b: Vector2d, color: Color, thickness: f32) {
+        self.debug.line(a, b, color, thickness);
+    }
+
+    pub(crate) fn debug_rect(&mut self, rect: &Rect, color: Color, thickness: f32) {
+        self.debug.rect(rect, color, thickness);
+    }
+
+    pub(crate) fn debug_circle(
+        &mut self,
+        center: Vector2d,
+        radius: f32,
+        color: Color,
+        thickness: f32,
+    ) {
+        self.debug.circle(center, radius, color, thickness);
+    }
+
+    pub(crate) fn debug_poly(&mut self, points: &[Vector2d], color: Color, thickness: f32) {
+        self.debug.poly(points, color, thickness);
+    }
+
+    // hands what was drawn since the last update to the render data and starts over
+    pub(crate) fn flush_debug_draw(&mut self) {
+        std::mem::swap(&mut self.debug, &mut self.vert_attrib.debug);
+        self.debug.clear();
+    }
+}
@@ -0,0 +1,98 @@
+use crate::*;
+
+impl Frame {
+    // for frames a packer turned a quarter clockwise to fit, puts the corners back so the top left
+    // of the frame is the top left of the art again
+    pub(crate) fn unrotated(self) -> Self {
+        Self {
+            top_left: self.top_right,
+            top_right: self.bottom_right,
+            bottom_right: self.bottom_left,
+            bottom_left: self.top_left,
+        }
+    }
+}
+
+impl Rect {
+    // the same top left with width and height traded, for the size of a frame packed on its side
+    pub(crate) fn transposed(&self) -> Self {
+        let (x, y, z) = self.top_left;
+        Rect::new_raw(x, y, z, self.height(), self.width())
+    }
+}
+
+impl NineSlice {
+    // the borders trade places along with the art
+    pub(crate) fn flipped(mut self, flip_x: bool, flip_y: bool) -> Self {
+        if flip_x {
+            std::mem::swap(&mut self.left, &mut self.right);
+        }
+        if flip_y {
+            std::mem::swap(&mut self.top, &mut self.bottom);
+        }
+        self
+    }
+}
+
+/// mirrors a piece of `rect` to the other side of it, for the float stencil's pieces
+pub(crate) fn mirror_piece(rect: &Rect, piece: &Rect, flip_x: bool, flip_y: bool) -> Rect {
+    let (start, end) = match (
+        rect.local_coords(piece.top_left()),
+        rect.local_coords(piece.bottom_right()),
+    ) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return *piece,
+    };
+    let (s0, s1) = if flip_x {
+        (1.0 - end.0, 1.0 - start.0)
+    } else {
+        (start.0, end.0)
+    };
+    let (t0, t1) = if flip_y {
+        (1.0 - end.1, 1.0 - start.1)
+    } else {
+        (start.1, end.1)
+    };
+    rect.sub_rect(s0, t0, s1, t1)
+}
+
+impl Scene {
+    /// marks an atlas entry as packed a quarter turn clockwise, sprites, buttons and emitters
+    /// using it from now on get it the right way up, as do the sequences already playing it
+    pub(crate) fn set_packed_rotated(
+        &mut self,
+        tex: &str,
+        is_rotated: bool,
+    ) -> Result<(), &'static str> {
+        self.tex_atlas.get(tex)?;
+        if is_rotated {
+            self.rotated_frames.insert(tex.to_string());
+        } else {
+            self.rotated_frames.remove(tex);
+        }
+        self.refresh_sequence_frames(tex)
+    }
+
+    // the atlas entry as it should be drawn
+    pub(crate) fn atlas_frame(&self, tex: &str) -> Result<(Frame, Rect), &'static str> {
+        let (frame, frame_rect) = self.tex_atlas.get(tex)?;
+        if self.rotated_frames.contains(tex) {
+            Ok((frame.unrotated(), frame_rect.transposed()))
+        } else {
+            Ok((frame, frame_rect))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transposed_keeps_top_left() {
+        let rect = Rect::new_raw(4.0, 8.0, 128.0, 16.0, 32.0);
+        let transposed = rect.transposed();
+        assert_eq!(transposed.top_left, rect.top_left);
+        assert_eq!((transposed.width(), transposed.height()), (32.0, 16.0));
+    }
+}
@@ -0,0 +1,239 @@
+use crate::*;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct GroupId(u32);
+
+// nothing in here is looked at during update, so the number of groups costs nothing per frame
+#[derive(Default)]
+pub(crate) struct Groups {
+    members: HashMap<GroupId, Vec<SpriteId>>,
+    next_id: u32,
+}
+
+impl Groups {
+    fn add(&mut self) -> GroupId {
+        let id = GroupId(self.next_id);
+        self.next_id += 1;
+        self.members.insert(id, vec![]);
+        id
+    }
+
+    fn insert(&mut self, group: GroupId, data: &mut SpriteData) -> Result<(), &'static str> {
+        let members = self.members.get_mut(&group).ok_or("invalid group id")?;
+        if !data.groups.contains(&group) {
+            data.groups.push(group);
+            members.push(data.id);
+        }
+        Ok(())
+    }
+
+    fn members(&self, group: GroupId) -> Result<&[SpriteId], &'static str> {
+        self.members
+            .get(&group)
+            .map(|members| members.as_slice())
+            .ok_or("invalid group id")
+    }
+
+    fn forget(&mut self, id: SpriteId, groups: &[GroupId]) {
+        for group in groups {
+            if let Some(members) = self.members.get_mut(group) {
+                if let Some(index) = members.iter().position(|each| *each == id) {
+                    members.swap_remove(index);
+                }
+            }
+        }
+    }
+}
+
+/// what a bulk operation applies to
+#[derive(Clone, Copy)]
+pub(crate) enum SpriteSet<'a> {
+    Tag(&'a str),
+    Group(GroupId),
+}
+
+impl Scene {
+    pub(crate) fn add_group(&mut self) -> GroupId {
+        self.groups.add()
+    }
+
+    // the sprites stay, they just stop being in the group
+    pub(crate) fn remove_group(&mut self, group: GroupId) -> Result<(), &'static str> {
+        let members = self
+            .groups
+            .members
+            .remove(&group)
+            .ok_or("invalid group id")?;
+        self.visibility.hidden_groups.remove(&group);
+        for id in members {
+            if let Ok(data) = self.sprite_data_mut(id) {
+                data.groups.retain(|each| *each != group);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn group_add(&mut self, group: GroupId, id: SpriteId) -> Result<(), &'static str> {
+        self.groups.members(group)?;
+        let (tl_key, _) = self.sprite_keys.get(&id).ok_or("invalid sprite id")?;
+        let data = match &mut self.part_map[tl_key] {
+            SpriteDataPoint::TopLeft(data) => data,
+            SpriteDataPoint::BottomRight => return Err("shouldn't be br point"),
+        };
+        self.groups.insert(group, data)
+    }
+
+    pub(crate) fn group_remove(
+        &mut self,
+        group: GroupId,
+        id: SpriteId,
+    ) -> Result<(), &'static str> {
+        self.groups.members(group)?;
+        self.groups.forget(id, &[group]);
+        self.sprite_data_mut(id)?
+            .groups
+            .retain(|each| *each != group);
+        Ok(())
+    }
+
+    // in the order they were made
+    pub(crate) fn group_ids(&self) -> Vec<GroupId> {
+        let mut ids: Vec<GroupId> = self.groups.members.keys().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    pub(crate) fn group_members(&self, group: GroupId) -> Result<&[SpriteId], &'static str> {
+        self.groups.members(group)
+    }
+
+    // called on removal
+    pub(crate) fn forget_groups(&mut self, id: SpriteId, groups: &[GroupId]) {
+        self.groups.forget(id, groups);
+    }
+
+    fn sprite_set(&self, set: SpriteSet) -> Vec<SpriteId> {
+        match set {
+            SpriteSet::Tag(tag) => self.sprites_with_tag(tag),
+            SpriteSet::Group(group) => self.group_members(group).unwrap_or(&[]).to_vec(),
+        }
+    }
+
+    fn for_each_in_set(&mut self, set: SpriteSet, mut f: impl FnMut(&mut SpriteData)) {
+        for id in self.sprite_set(set) {
+            if let Ok(data) = self.sprite_data_mut(id) {
+                f(data);
+            }
+        }
+    }
+
+    pub(crate) fn hide_sprites(&mut self, set: SpriteSet) {
+        self.for_each_in_set(set, |data| data.is_visible = false);
+    }
+
+    pub(crate) fn show_sprites(&mut self, set: SpriteSet) {
+        self.for_each_in_set(set, |data| data.is_visible = true);
+    }
+
+    pub(crate) fn tint_sprites(&mut self, set: SpriteSet, r: f32, g: f32, b: f32) {
+        self.for_each_in_set(set, |data| {
+            data.tint.r = r;
+            data.tint.g = g;
+            data.tint.b = b;
+        });
+    }
+
+    pub(crate) fn pause_sprites(&mut self, set: SpriteSet, is_paused: bool) {
+        self.for_each_in_set(set, |data| data.is_paused = is_paused);
+    }
+
+    /// prefabs in the set bring the rest of themselves along. returns the sprites that would've
+    /// ended up off the map, those stay where they were
+    pub(crate) fn move_sprites(&mut self, set: SpriteSet, delta: Vector2d) -> Vec<SpriteId> {
+        let ids = self.sprite_set(set);
+        // so the parts of a prefab that are in the set as well don't get moved twice
+        let carried: HashSet<SpriteId> = ids
+            .iter()
+            .filter_map(|id| self.attached.get(id))
+            .flatten()
+            .copied()
+            .collect();
+        let mut stuck = vec![];
+        for id in ids {
+            if carried.contains(&id) {
+                continue;
+            }
+            match self.transform_sprite(id, |data| data.rect.offset_pos(delta)) {
+                Ok(()) => self.move_attached(id, delta),
+                // the rest of its prefab stays with it
+                Err(_) if self.sprite_data(id).is_ok() => stuck.push(id),
+                Err(_) => {}
+            }
+        }
+        stuck
+    }
+
+    /// handles to the removed sprites are left dangling, they can only be dropped
+    pub(crate) fn remove_sprites(&mut self, set: SpriteSet) {
+        for id in self.sprite_set(set) {
+            // prefab roots take sprites later in the set with them
+            self.remove_sprite_raw(id).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paused_sprite_keeps_its_frame() {
+        let rect = Rect::new_raw(0.0, 0.0, 128.0, 16.0, 16.0);
+        let frame = Frame::from_uv(0.0, 0.0, 1.0, 1.0);
+        let mut data = SpriteData::new(SpriteId(0), rect, frame, &rect);
+        assert_eq!(data.anim_step(0.25), Some(0.25));
+        data.is_paused = true;
+        assert_eq!(data.anim_step(0.25), None);
+        // the sequence hands back the frame it was on, which leaves the sprite as it was
+        assert!(!data.animate(frame, &rect));
+    }
+
+    fn sprite(id: u32) -> SpriteData {
+        let rect = Rect::new_raw(0.0, 0.0, 128.0, 16.0, 16.0);
+        SpriteData::new(
+            SpriteId(id),
+            rect,
+            Frame::from_uv(0.0, 0.0, 1.0, 1.0),
+            &rect,
+        )
+    }
+
+    #[test]
+    fn adding_twice_keeps_one_entry() {
+        let mut groups = Groups::default();
+        let (first, second) = (groups.add(), groups.add());
+        assert!(first < second);
+        let mut data = sprite(0);
+        groups.insert(first, &mut data).unwrap();
+        groups.insert(first, &mut data).unwrap();
+        groups.insert(second, &mut data).unwrap();
+        assert_eq!(data.groups, vec![first, second]);
+        assert_eq!(groups.members(first).unwrap(), &[SpriteId(0)]);
+        assert!(groups.insert(GroupId(2), &mut data).is_err());
+        assert!(groups.members(GroupId(2)).is_err());
+    }
+
+    #[test]
+    fn forget_drops_the_member_from_each_group() {
+        let mut groups = Groups::default();
+        let (first, second) = (groups.add(), groups.add());
+        let (mut kept, mut removed) = (sprite(0), sprite(1));
+        groups.insert(first, &mut kept).unwrap();
+        groups.insert(first, &mut removed).unwrap();
+        groups.insert(second, &mut removed).unwrap();
+        // like remove_sprite does with the groups the sprite was in
+        groups.forget(SpriteId(1), &removed.groups);
+        assert_eq!(groups.members(first).unwrap(), &[SpriteId(0)]);
+        assert!(groups.members(second).unwrap().is_empty());
+    }
+}
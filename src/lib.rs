@@ -1,29 +1,77 @@
 #![allow(unused_variables, unused_imports, dead_code, unused_mut)]
 #![feature(portable_simd)]
 mod animation;
+mod camera;
+mod clip;
+mod debug;
+mod flip;
+mod group;
+mod overlay;
+mod parallax;
+mod particle;
+mod pick;
+mod pixel;
+mod prefab;
 mod quadtree;
+mod save;
+mod sequence;
+mod slice;
+mod stats;
+mod stencil;
+mod tag;
+mod target;
+mod text;
+mod tiled;
+mod tilemap;
+mod tween;
+mod ui;
+mod viewport;
+mod visibility;
+mod xml;
 use animation::*;
-use arrayvec::ArrayString;
+use arrayvec::{ArrayString, ArrayVec};
+use camera::*;
+use clip::*;
+use debug::*;
+use flip::*;
+use group::*;
+use overlay::*;
+use parallax::*;
+use particle::*;
+use pick::*;
+use pixel::*;
+use prefab::*;
 use quadtree::*;
-use std::collections::HashMap;
-use std::ops::{Index, IndexMut};
+use save::*;
+use sequence::*;
+use serde::{Deserialize, Serialize};
+use slice::*;
+use stats::*;
+use std::collections::{HashMap, HashSet};
+use std::ops::{Index, IndexMut, Range};
 use std::time::Duration;
-use std::time::Instant;
 use std::{
     f32::consts::PI,
     ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign},
 };
+use stencil::*;
+use tag::*;
+use target::*;
+use text::*;
+use tiled::*;
+use tilemap::*;
+use tween::*;
+use ui::*;
+use viewport::*;
+use visibility::*;
 use wasm_bindgen::prelude::wasm_bindgen;
+use xml::*;
 
 // todo, ok but what about a simulation game just like simtower/project highrise but with trains
 // you can design trains and attach them onto each other, each carriage is a module, maybe one can act
 // as a transformer that turns DC to AC, where the DC power come from another carriage, probably nuclear
 // or something... sleeper ones, seat ones and other kinds, dining area, water spa train, shop train
 
-// todo, render to texture
-
-// todo bezier curve using the above linear transformation to achieve graceful animation
-
 #[repr(C)]
 #[derive(Clone, Copy, PartialEq)]
 struct Vector2d {
@@ -43,8 +91,11 @@ impl Vector2d {
         // y2 = sinβx1 + cosβy1
         // this formula is counterclockwise rotation
         let angle = 2.0 * PI - rad;
-        self.x = angle.cos() * self.x - angle.sin() * self.y;
-        self.y = angle.sin() * self.x - angle.cos() * self.y;
+        let (sin, cos) = angle.sin_cos();
+        let x = cos * self.x - sin * self.y;
+        let y = sin * self.x + cos * self.y;
+        self.x = x;
+        self.y = y;
     }
 
     fn to_origin(self) -> Origin {
@@ -117,6 +168,17 @@ impl Sub for Vector2d {
     }
 }
 
+impl Mul<f32> for Vector2d {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Self {
+            x: self.x * rhs,
+            y: self.y * rhs,
+        }
+    }
+}
+
 impl Neg for Vector2d {
     type Output = Self;
 
@@ -127,72 +189,84 @@ impl Neg for Vector2d {
     }
 }
 
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Color {
+    r: f32,
+    g: f32,
+    b: f32,
+    a: f32,
+}
+impl Color {
+    fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    fn white() -> Self {
+        Self::new(1.0, 1.0, 1.0, 1.0)
+    }
+
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Self {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+            a: self.a + (other.a - self.a) * t,
+        }
+    }
+}
+
+#[repr(transparent)]
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct SpriteId(u32);
+
 // there's only one copy of Sprite, once it's gone all the sprites are gone
 struct Sprite {
-    // but now we use quadtree to store both rect and texture how do we remotely handle this??
+    // the keys move around in the partition map every time the rect changes, so the scene keeps
+    // track of them and the handle only holds on to a stable id
     scene: *mut Scene,
-    key_top_left: Option<Key>,
-    key_bottom_right: Option<Key>,
-    origin: Origin,
+    id: SpriteId,
 }
 impl Sprite {
     fn read_data<'a, 'b>(&'a self) -> &'b SpriteData {
-        unsafe {
-            match &(*self.scene).part_map[self.key_top_left.as_ref().unwrap()] {
-                SpriteDataPoint::TopLeft(data) => data,
-                SpriteDataPoint::BottomRight => panic!("shouldn't be br point"),
-            }
-        }
+        unsafe { (*self.scene).sprite_data(self.id).unwrap() }
     }
 
     fn read_data_mut<'a, 'b>(&'a mut self) -> &'b mut SpriteData {
-        unsafe {
-            match &mut (*self.scene).part_map[self.key_top_left.as_ref().unwrap()] {
-                SpriteDataPoint::TopLeft(data) => data,
-                SpriteDataPoint::BottomRight => panic!("shouldn't be br point"),
-            }
-        }
+        unsafe { (*self.scene).sprite_data_mut(self.id).unwrap() }
     }
 
     fn update_keys(&mut self) {
-        let bound_rect = self.read_data().rect.get_bounding_rect();
-        self.key_top_left = Some(unsafe {
-            (*self.scene)
-                .part_map
-                .move_point(
-                    self.key_top_left.as_ref().unwrap().copy_internal(),
-                    bound_rect.top_left(),
-                )
-                .unwrap()
-        });
-        self.key_bottom_right = Some(unsafe {
-            (*self.scene)
-                .part_map
-                .move_point(
-                    self.key_bottom_right.as_ref().unwrap().copy_internal(),
-                    bound_rect.bottom_right(),
-                )
-                .unwrap()
-        });
+        unsafe { (*self.scene).update_keys(self.id).unwrap() }
+    }
+
+    fn id(&self) -> SpriteId {
+        self.id
     }
 
     // will reset rect size to size of texture
     fn reset_size(&mut self) {
-        todo!()
+        let native_size = self.read_data().native_size;
+        self.set_size(native_size.x, native_size.y);
+    }
+
+    // how the rect follows when the frame changes
+    fn set_sizing(&mut self, sizing: SizingPolicy) {
+        self.read_data_mut().sizing = sizing;
     }
 
     fn reset_origin(&mut self) {
-        self.origin = Origin::zero();
+        self.read_data_mut().origin = Origin::zero();
     }
 
     // relative to 0,0 which is the top left of the sprite rect
     fn set_origin(&mut self, offset_x: f32, offset_y: f32) {
-        self.origin.set(offset_x, offset_y);
+        self.read_data_mut().origin.set(offset_x, offset_y);
     }
 
     // of origin
     fn get_pos_origin_global(&self) -> Vector2d {
-        self.read_data().rect.top_left() + self.origin.vector2d
+        self.read_data().origin_global()
     }
 
     fn get_pos_top_left(&self) -> Vector2d {
@@ -212,81 +286,211 @@ impl Sprite {
 
     // sets position according to the origin
     fn set_pos(&mut self, x: f32, y: f32) {
-        self.offset_pos(Vector2d { x, y } - self.get_pos_origin_global());
+        let before = self.read_data().origin_global();
+        self.read_data_mut().set_pos(x, y);
+        self.update_keys();
+        unsafe { (*self.scene).move_attached(self.id, Vector2d { x, y } - before) }
     }
 
     fn offset_pos(&mut self, vector: Vector2d) {
         self.read_data_mut().rect.offset_pos(vector);
         self.update_keys();
+        unsafe { (*self.scene).move_attached(self.id, vector) }
     }
 
+    // along the sprite's own edges, so rotated sprites keep their shape, an edge with no length
+    // can't be scaled up to one and is left as is
     fn set_size(&mut self, width: f32, height: f32) {
-        let x_scale = width / self.read_data().rect.width();
-        let y_scale = height / self.read_data().rect.height();
-        self.set_scale(x_scale, y_scale);
+        let (current_width, current_height) = self.read_data().rect.edge_lengths();
+        let scale = |to: f32, from: f32| if from != 0.0 { to / from } else { 1.0 };
+        self.set_scale(scale(width, current_width), scale(height, current_height));
     }
 
     fn set_scale(&mut self, x_scale: f32, y_scale: f32) {
-        self.origin =
-            self.read_data_mut()
-                .rect
-                .set_scale_with_origin(x_scale, y_scale, self.origin);
+        self.read_data_mut().set_scale(x_scale, y_scale);
         self.update_keys();
+        unsafe { (*self.scene).scale_attached(self.id, x_scale, y_scale) }
     }
 
     // totally gonna be careful here, need to get a bounding box whose lines are parallel to axis
     fn set_rotation(&mut self, rad: f32) {
-        self.origin = self
-            .read_data_mut()
-            .rect
-            .set_rotation_with_origin(rad, self.origin);
+        self.read_data_mut().set_rotation(rad);
         self.update_keys();
+        unsafe { (*self.scene).rotate_attached(self.id, rad) }
+    }
+
+    // rgb multiplied onto the texture, alpha is left as is
+    fn set_tint(&mut self, r: f32, g: f32, b: f32) {
+        let tint = &mut self.read_data_mut().tint;
+        tint.r = r;
+        tint.g = g;
+        tint.b = b;
+    }
+
+    fn set_opacity(&mut self, opacity: f32) {
+        self.read_data_mut().tint.a = opacity;
     }
 
     fn set_frame(&mut self, tex: &str) -> Result<(), &'static str> {
         self.disable_float_stencil();
-        // note that this only uses the texture and not the default size
+        // whether the default size gets used is up to the sizing policy
         unsafe {
-            let (frame, _) = (*self.scene).tex_atlas.get(tex)?;
-            self.read_data_mut().frame = frame;
+            let entry = (*self.scene).atlas_entry(tex)?;
+            let data = self.read_data_mut();
+            data.apply_frame(entry.frame, &entry.frame_rect);
+            data.texture = None;
+            data.nine_slice = entry.nine_slice;
+            data.frame_name = Some(tex.to_string());
         }
+        self.update_keys();
         Ok(())
     }
 
-    // only would have an effect if it's an animated texture, that is, a slice of frames with len longer than one
-    fn play(&mut self, seq: &str) -> Result<(), &'static str> {
-        // flush the animation index regardless
+    // shows everything that got drawn into a render target instead of an atlas frame
+    fn set_frame_from_target(&mut self, target: RenderTargetId) -> Result<(), &'static str> {
         unsafe {
-            let anim_index = (*self.scene).anim_seq.add(seq)?;
+            if !(*self.scene).render_targets.contains_key(&target) {
+                return Err("invalid render target id");
+            }
         }
-        todo!()
+        self.disable_float_stencil();
+        let data = self.read_data_mut();
+        data.frame = Frame::from_uv(0.0, 0.0, 1.0, 1.0);
+        data.texture = Some(target);
+        data.frame_name = None;
+        Ok(())
+    }
+
+    // only would have an effect if it's an animated texture, that is, a slice of frames with len longer than one,
+    // added with Scene::add_sequence
+    fn play(&mut self, seq: &str) -> Result<(), &'static str> {
+        unsafe { (*self.scene).play_sprite(self.id, seq) }
+    }
+
+    // mirrors what's drawn without touching the rect, so the origin and bounds stay put
+    fn set_flip(&mut self, flip_x: bool, flip_y: bool) {
+        let data = self.read_data_mut();
+        data.flip_x = flip_x;
+        data.flip_y = flip_y;
+    }
+
+    // hidden sprites keep their place in the scene, they're just skipped when drawing and picking
+    fn set_visible(&mut self, is_visible: bool) {
+        self.read_data_mut().is_visible = is_visible;
+    }
+
+    fn is_visible(&self) -> bool {
+        self.read_data().is_visible
     }
 
+    // the animation holds on to its current frame
     fn pause(&mut self) {
-        todo!()
+        self.read_data_mut().is_paused = true;
+    }
+
+    fn resume(&mut self) {
+        self.read_data_mut().is_paused = false;
     }
 
     fn end(&mut self) {
         todo!()
     }
 
-    fn enable_float_stencil(&mut self, bound: (), is_wrapping: bool) {
-        // after setting this, the set
-        todo!()
+    // the current frame becomes a window that can be scrolled around within the `bound` frame
+    fn enable_float_stencil(&mut self, bound: &str, is_wrapping: bool) -> Result<(), &'static str> {
+        unsafe {
+            let now = (*self.scene).time;
+            let (bound, _) = (*self.scene).tex_atlas.get(bound)?;
+            let data = self.read_data_mut();
+            data.stencil = Some(FloatStencil::new(bound, data.frame, is_wrapping, now)?);
+        }
+        Ok(())
     }
 
     fn disable_float_stencil(&mut self) {
-        todo!()
+        self.read_data_mut().stencil = None;
     }
 
-    fn render_to_texture(&mut self) {
-        todo!()
+    // in uv per second
+    fn set_scroll_velocity(&mut self, x: f32, y: f32) -> Result<(), &'static str> {
+        let now = unsafe { (*self.scene).time };
+        self.read_data_mut()
+            .stencil
+            .as_mut()
+            .ok_or("float stencil not enabled")?
+            .set_velocity(x, y, now);
+        Ok(())
     }
 
-    // some kinda special clipping sprite that many sprites can be under???
-    // either this or entire opaque layer
-    fn set_clipping_rect() {
-        todo!()
+    // in uv, relative to the top left of the bound
+    fn set_scroll_offset(&mut self, x: f32, y: f32) -> Result<(), &'static str> {
+        let now = unsafe { (*self.scene).time };
+        self.read_data_mut()
+            .stencil
+            .as_mut()
+            .ok_or("float stencil not enabled")?
+            .set_offset(x, y, now);
+        Ok(())
+    }
+
+    // the sprite gets drawn into the target instead of onto the screen, None to put it back
+    fn render_to_texture(&mut self, target: Option<RenderTargetId>) {
+        self.read_data_mut().render_target = target;
+    }
+
+    // borders that keep their size when the sprite is resized, None to stretch the whole frame,
+    // set_frame goes back to whatever the atlas entry has in Scene::set_slice_insets
+    fn set_nine_slice(&mut self, slice: Option<NineSlice>) {
+        self.read_data_mut().nine_slice = slice;
+    }
+
+    // whether pointer events get delivered to it, sprites that aren't let the pointer through
+    fn set_interactive(&mut self, is_interactive: bool) {
+        self.read_data_mut().is_interactive = is_interactive;
+    }
+
+    // only the solid parts of the frame get hit, None for the whole rect
+    fn set_hit_mask(&mut self, mask: Option<HitMaskId>) {
+        self.read_data_mut().hit_mask = mask;
+    }
+
+    // repeats the sprite forever along x and/or y, for backgrounds, see Scene::set_layer_parallax
+    fn set_repeat(&mut self, x: bool, y: bool) {
+        let repeat = Repeat { x, y };
+        self.read_data_mut().repeat = repeat;
+        unsafe {
+            let repeating = &mut (*self.scene).parallax.repeating;
+            if repeat.any() {
+                repeating.insert(self.id);
+            } else {
+                repeating.remove(&self.id);
+            }
+        }
+    }
+
+    // puts the sprite under a clip made with Scene::add_clip, None to take it out again
+    fn set_clipping_rect(&mut self, clip: Option<ClipId>) {
+        self.read_data_mut().clip = clip;
+    }
+
+    fn add_tag(&mut self, tag: &str) {
+        unsafe { (*self.scene).tag_sprite(self.id, tag).unwrap() };
+    }
+
+    fn remove_tag(&mut self, tag: &str) {
+        unsafe { (*self.scene).untag_sprite(self.id, tag).unwrap() }
+    }
+
+    fn has_tag(&self, tag: &str) -> bool {
+        unsafe { (*self.scene).has_tag(self.id, tag) }
+    }
+
+    fn add_to_group(&mut self, group: GroupId) -> Result<(), &'static str> {
+        unsafe { (*self.scene).group_add(group, self.id) }
+    }
+
+    fn remove_from_group(&mut self, group: GroupId) -> Result<(), &'static str> {
+        unsafe { (*self.scene).group_remove(group, self.id) }
     }
 
     fn remove(self) {
@@ -296,12 +500,8 @@ impl Sprite {
 impl Drop for Sprite {
     fn drop(&mut self) {
         unsafe {
-            (*self.scene)
-                .remove_sprite_raw(
-                    self.key_top_left.as_ref().unwrap().copy_internal(),
-                    self.key_bottom_right.as_ref().unwrap().copy_internal(),
-                )
-                .unwrap();
+            // Scene::remove_sprites might've gotten to it first
+            (*self.scene).remove_sprite_raw(self.id).ok();
         }
     }
 }
@@ -309,7 +509,7 @@ impl Drop for Sprite {
 /// this is a base level concept, don't deal with it directly
 /// the coords system for this is right -> x++ and down -> y++
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Rect {
     top_left: (f32, f32, f32),
     bottom_left: (f32, f32, f32),
@@ -338,8 +538,8 @@ impl Rect {
 
     fn set_raw(&mut self, x: f32, y: f32, z: f32, width: f32, height: f32) {
         self.top_left = (x, y, z);
-        self.bottom_left = (x, y - height, z);
-        self.bottom_right = (x + width, y - height, z);
+        self.bottom_left = (x, y + height, z);
+        self.bottom_right = (x + width, y + height, z);
         self.top_right = (x + width, y, z);
     }
 
@@ -348,9 +548,12 @@ impl Rect {
         self.set_raw(x, y, self.top_left.2, self.width(), self.height());
     }
 
-    fn offset_pos(&mut self, mut vector: Vector2d) {
-        vector += self.top_left();
-        self.set_pos_top_left(vector.x, vector.y);
+    // moves every corner so a rotated rect stays rotated
+    fn offset_pos(&mut self, vector: Vector2d) {
+        self.set_top_left(self.top_left() + vector);
+        self.set_top_right(self.top_right() + vector);
+        self.set_bottom_left(self.bottom_left() + vector);
+        self.set_bottom_right(self.bottom_right() + vector);
     }
 
     // offset by the size of self
@@ -431,16 +634,17 @@ impl Rect {
     }
 
     fn get_bounding_rect(&self) -> Self {
-        let top = self.top_left().y.max(
+        // y goes down, so the top is the smallest y
+        let top = self.top_left().y.min(
             self.top_right()
                 .y
-                .max(self.bottom_left().y.max(self.bottom_right().y)),
+                .min(self.bottom_left().y.min(self.bottom_right().y)),
         );
 
-        let bottom = self.top_left().y.min(
+        let bottom = self.top_left().y.max(
             self.top_right()
                 .y
-                .min(self.bottom_left().y.min(self.bottom_right().y)),
+                .max(self.bottom_left().y.max(self.bottom_right().y)),
         );
 
         let right = self.top_left().x.max(
@@ -455,10 +659,10 @@ impl Rect {
                 .min(self.bottom_left().x.min(self.bottom_right().x)),
         );
         Self {
-            top_left: (top, left, self.top_left.2),
-            bottom_left: (bottom, left, self.bottom_left.2),
-            bottom_right: (bottom, right, self.bottom_right.2),
-            top_right: (top, right, self.top_right.2),
+            top_left: (left, top, self.top_left.2),
+            bottom_left: (left, bottom, self.bottom_left.2),
+            bottom_right: (right, bottom, self.bottom_right.2),
+            top_right: (right, top, self.top_right.2),
         }
     }
 
@@ -484,7 +688,7 @@ impl Rect {
         let mut br = self.bottom_right() - origin_global;
         br.set_rotation(rad);
         br += origin_global;
-        self.set_top_right(br);
+        self.set_bottom_right(br);
 
         (origin_global - tl).to_origin()
     }
@@ -511,7 +715,7 @@ impl Rect {
         let mut br = self.bottom_right() - origin_global;
         br.set_scale(x_scale, y_scale);
         br += origin_global;
-        self.set_top_right(br);
+        self.set_bottom_right(br);
 
         (origin_global - tl).to_origin()
     }
@@ -524,6 +728,65 @@ impl Rect {
         .to_origin()
     }
 
+    // s and t go from 0 to 1 across the rect, works for rotated rects too
+    fn point_at(&self, s: f32, t: f32) -> Vector2d {
+        self.top_left()
+            + (self.top_right() - self.top_left()) * s
+            + (self.bottom_left() - self.top_left()) * t
+    }
+
+    // the other way around from point_at, None for a rect with no area
+    fn local_coords(&self, point: Vector2d) -> Option<(f32, f32)> {
+        let across = self.top_right() - self.top_left();
+        let down = self.bottom_left() - self.top_left();
+        let offset = point - self.top_left();
+        let det = across.x * down.y - across.y * down.x;
+        if det == 0.0 {
+            return None;
+        }
+        Some((
+            (offset.x * down.y - offset.y * down.x) / det,
+            (across.x * offset.y - across.y * offset.x) / det,
+        ))
+    }
+
+    fn sub_rect(&self, s0: f32, t0: f32, s1: f32, t1: f32) -> Self {
+        let z = self.top_left.2;
+        let tl = self.point_at(s0, t0);
+        let bl = self.point_at(s0, t1);
+        let br = self.point_at(s1, t1);
+        let tr = self.point_at(s1, t0);
+        Self {
+            top_left: (tl.x, tl.y, z),
+            bottom_left: (bl.x, bl.y, z),
+            bottom_right: (br.x, br.y, z),
+            top_right: (tr.x, tr.y, z),
+        }
+    }
+
+    // lengths of the top and left edges, unlike width/height these hold up under rotation
+    fn edge_lengths(&self) -> (f32, f32) {
+        let top = self.top_right() - self.top_left();
+        let left = self.bottom_left() - self.top_left();
+        (
+            (top.x * top.x + top.y * top.y).sqrt(),
+            (left.x * left.x + left.y * left.y).sqrt(),
+        )
+    }
+
+    fn is_axis_aligned(&self) -> bool {
+        self.top_left.1 == self.top_right.1 && self.top_left.0 == self.bottom_left.0
+    }
+
+    // of the bounding rects, so rotated rects overlap a bit more than they really do
+    fn overlaps(&self, other: &Rect) -> bool {
+        let (a, b) = (self.get_bounding_rect(), other.get_bounding_rect());
+        a.top_left().x <= b.bottom_right().x
+            && b.top_left().x <= a.bottom_right().x
+            && a.top_left().y <= b.bottom_right().y
+            && b.top_left().y <= a.bottom_right().y
+    }
+
     fn center_global(&self) -> Vector2d {
         self.top_left()
             + Vector2d {
@@ -537,28 +800,111 @@ impl Rect {
 struct WasmVertAttribPtr {
     sprite_pos: *const u8,
     tex_pos: *const u8,
+    color: *const u8,
     index: *const u8,
+    batches: *const u8,
+    masks: *const u8,
+    passes: *const u8,
+    text_styles: *const u8,
+    debug: *const u8,
+    stats: *const u8,
 
     sprite_pos_len: u32,
     tex_pos_len: u32,
+    color_len: u32,
     index_len: u32,
+    batches_len: u32,
+    masks_len: u32,
+    passes_len: u32,
+    text_styles_len: u32,
+    debug_len: u32,
+    stats_len: u32,
+}
+
+#[cfg_attr(target_family = "wasm", wasm_bindgen)]
+struct WasmPointerEventPtr {
+    events: *const u8,
+    events_len: u32,
+}
+
+impl WasmPointerEventPtr {
+    fn new(events: &[PointerEvent]) -> Self {
+        WasmPointerEventPtr {
+            events: events.as_ptr() as _,
+            events_len: events.len() as u32 * 4,
+        }
+    }
 }
 
 impl WasmVertAttribPtr {
-    fn new(render_data: &RenderData) -> Self {
+    fn new(render_data: &RenderData, stats: &FrameStats) -> Self {
         WasmVertAttribPtr {
             sprite_pos: render_data.sprite_pos.as_ptr() as _,
             sprite_pos_len: render_data.sprite_pos.len() as u32 * 12,
             tex_pos: render_data.tex_pos.as_ptr() as _,
             tex_pos_len: render_data.tex_pos.len() as u32 * 8,
+            color: render_data.color.as_ptr() as _,
+            color_len: render_data.color.len() as u32 * 16,
             index: render_data.index.as_ptr() as _,
             index_len: render_data.sprite_pos.len() as u32 * 6,
+            batches: render_data.batches.as_ptr() as _,
+            batches_len: render_data.batches.len() as u32 * 5,
+            masks: render_data.masks.as_ptr() as _,
+            masks_len: render_data.masks.len() as u32 * 12,
+            passes: render_data.passes.as_ptr() as _,
+            passes_len: render_data.passes.len() as u32 * 13,
+            text_styles: render_data.text_styles.as_ptr() as _,
+            text_styles_len: render_data.text_styles.len() as u32 * 14,
+            debug: render_data.debug.vertices().as_ptr() as _,
+            debug_len: render_data.debug.vertices().len() as u32 * 6,
+            stats: stats as *const FrameStats as _,
+            stats_len: std::mem::size_of::<FrameStats>() as u32 / 4,
         }
     }
 }
 
+// one bit for every depth a layer can have, see Sprite::set_layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LayerMask([u64; 4]);
+impl LayerMask {
+    fn none() -> Self {
+        Self([0; 4])
+    }
+
+    fn all() -> Self {
+        Self([!0; 4])
+    }
+
+    fn with(mut self, layer: u8, is_ui: bool) -> Self {
+        let depth = if is_ui {
+            layer as usize
+        } else {
+            layer as usize + 128
+        };
+        self.0[depth / 64] |= 1 << (depth % 64);
+        self
+    }
+
+    fn without(mut self, layer: u8, is_ui: bool) -> Self {
+        let depth = if is_ui {
+            layer as usize
+        } else {
+            layer as usize + 128
+        };
+        self.0[depth / 64] &= !(1 << (depth % 64));
+        self
+    }
+
+    // takes the depth of a rect
+    fn contains(&self, depth: f32) -> bool {
+        let depth = (depth as usize).min(255);
+        self.0[depth / 64] & (1 << (depth % 64)) != 0
+    }
+}
+
 // this is for occlusion only, you need to do a matrix transformation in shader to get the correct
 // rendering
+#[derive(Clone, Copy)]
 struct Camera {
     rect: Rect,
 }
@@ -576,29 +922,287 @@ impl Camera {
             .set_pos_with_origin(self.rect.center_origin(), x, y);
     }
 
-    fn set_zoom_level(&mut self, scale: f32) {
+    // `grid` is Scene::zoom_grid, with pixel snapping on the zoom lands on the closest whole
+    // number of screen pixels per art pixel
+    fn set_zoom_level(&mut self, scale: f32, grid: Option<(Vector2d, f32)>) {
         self.rect
             .set_scale_with_origin(scale, scale, self.rect.center_origin());
+        if let Some((screen, pixels_per_unit)) = grid {
+            *self = self.snapped_to_integer_zoom(screen, pixels_per_unit);
+        }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum SizingPolicy {
+    // the frame gets stretched over whatever the rect is
+    KeepSize,
+    // the rect follows the size and trim offset of the frame at the current scale
+    MatchFrame,
+    // keeps the width, the height follows the aspect ratio of the frame
+    KeepAspectRatio,
+}
+
 struct SpriteData {
+    id: SpriteId,
     rect: Rect,
     frame: Frame,
-    anim_key: Option<AnimationIndex>,
+    playback: Option<Playback>,
+    // the atlas entry the frame came from, None when it shows a render target
+    frame_name: Option<String>,
+    // the name of the sequence playback is in
+    playing: Option<String>,
+    origin: Origin,
+    // accumulated from all the set_scale/set_rotation calls, the rect itself doesn't remember them
+    scale: Vector2d,
+    rotation: f32,
+    // the rect from before a zero scale flattened it, with its origin and scale
+    collapsed: Option<(Rect, Origin, Vector2d)>,
+    tint: Color,
+    stencil: Option<FloatStencil>,
+    clip: Option<ClipId>,
+    // where the sprite gets drawn, None for the screen
+    render_target: Option<RenderTargetId>,
+    // ui sprites are positioned in screen pixels and drawn on top of every camera
+    is_screen_space: bool,
+    // what the frame samples from, None for the atlas
+    texture: Option<RenderTargetId>,
+    // size of the current frame in the atlas and where it sits within its untrimmed source
+    native_size: Vector2d,
+    trim_offset: Vector2d,
+    sizing: SizingPolicy,
+    nine_slice: Option<NineSlice>,
+    // gets pointer events
+    is_interactive: bool,
+    hit_mask: Option<HitMaskId>,
+    // tiled over the whole view along these axes
+    repeat: Repeat,
+    tags: Vec<TagId>,
+    groups: Vec<GroupId>,
+    // hidden sprites stay in the part map but aren't drawn or picked
+    is_visible: bool,
+    flip_x: bool,
+    flip_y: bool,
+    is_paused: bool,
+}
+impl SpriteData {
+    // frame_rect is the one from the atlas
+    fn new(id: SpriteId, rect: Rect, frame: Frame, frame_rect: &Rect) -> Self {
+        Self {
+            id,
+            rect,
+            frame,
+            playback: None,
+            frame_name: None,
+            playing: None,
+            origin: Origin::zero(),
+            scale: Vector2d { x: 1.0, y: 1.0 },
+            rotation: 0.0,
+            collapsed: None,
+            tint: Color::white(),
+            stencil: None,
+            clip: None,
+            render_target: None,
+            is_screen_space: false,
+            texture: None,
+            native_size: Vector2d {
+                x: frame_rect.width(),
+                y: frame_rect.height(),
+            },
+            trim_offset: frame_rect.top_left(),
+            sizing: SizingPolicy::KeepSize,
+            nine_slice: None,
+            is_interactive: false,
+            hit_mask: None,
+            repeat: Repeat::default(),
+            tags: vec![],
+            groups: vec![],
+            is_visible: true,
+            flip_x: false,
+            flip_y: false,
+            is_paused: false,
+        }
+    }
+
+    /// swaps in a frame from the atlas, resizing the rect according to the sizing policy
+    fn apply_frame(&mut self, frame: Frame, frame_rect: &Rect) {
+        let native = Vector2d {
+            x: frame_rect.width(),
+            y: frame_rect.height(),
+        };
+        let trim = frame_rect.top_left();
+        let is_degenerate = native.x <= 0.0
+            || native.y <= 0.0
+            || self.native_size.x <= 0.0
+            || self.native_size.y <= 0.0;
+        // animations swap frames every update, ones the same size leave the rect alone
+        let is_same_size = native == self.native_size && trim == self.trim_offset;
+
+        // the new rect in units of the current one, which works for rotated rects too
+        let (s0, t0, s1, t1) = match self.sizing {
+            _ if is_degenerate || is_same_size => (0.0, 0.0, 1.0, 1.0),
+            SizingPolicy::KeepSize => (0.0, 0.0, 1.0, 1.0),
+            // keeping the untrimmed source in place is what stops animations from jittering
+            SizingPolicy::MatchFrame => {
+                let s0 = (trim.x - self.trim_offset.x) / self.native_size.x;
+                let t0 = (trim.y - self.trim_offset.y) / self.native_size.y;
+                (
+                    s0,
+                    t0,
+                    s0 + native.x / self.native_size.x,
+                    t0 + native.y / self.native_size.y,
+                )
+            }
+            SizingPolicy::KeepAspectRatio => {
+                let (width, height) = self.rect.edge_lengths();
+                (0.0, 0.0, 1.0, (native.y / native.x) * (width / height))
+            }
+        };
+        if (s0, t0, s1, t1) != (0.0, 0.0, 1.0, 1.0) {
+            let origin_global = self.origin_global();
+            self.rect = self.rect.sub_rect(s0, t0, s1, t1);
+            self.origin = (origin_global - self.rect.top_left()).to_origin();
+        }
+
+        self.frame = frame;
+        self.native_size = native;
+        self.trim_offset = trim;
+    }
+
+    // how far its sequence moves on this frame, a paused one stays on the frame it shows
+    fn anim_step(&self, delta_t: f32) -> Option<f32> {
+        if self.is_paused {
+            None
+        } else {
+            Some(delta_t)
+        }
+    }
+
+    // the current frame of the sequence it plays, returns whether the rect changed
+    fn animate(&mut self, frame: Frame, frame_rect: &Rect) -> bool {
+        let before = self.rect;
+        self.apply_frame(frame, frame_rect);
+        self.rect != before
+    }
+
+    fn origin_global(&self) -> Vector2d {
+        self.rect.top_left() + self.origin.vector2d
+    }
+
+    fn set_pos(&mut self, x: f32, y: f32) {
+        self.rect
+            .offset_pos(Vector2d { x, y } - self.origin_global());
+    }
+
+    fn set_scale(&mut self, x_scale: f32, y_scale: f32) {
+        self.scale_to(Vector2d {
+            x: self.scale.x * x_scale,
+            y: self.scale.y * y_scale,
+        });
+    }
+
+    /// sets the scale outright instead of relative to the current one, unlike set_scale this can
+    /// bring a sprite back from a zero scale
+    fn scale_to(&mut self, scale: Vector2d) {
+        if let Some((rect, origin, from)) = self.collapsed.take() {
+            let shift = self.origin_global() - (rect.top_left() + origin.vector2d);
+            self.rect = rect;
+            self.rect.offset_pos(shift);
+            self.origin = origin;
+            self.scale = from;
+        }
+        if scale.x == 0.0 || scale.y == 0.0 {
+            self.collapsed = Some((self.rect, self.origin, self.scale));
+        }
+        let ratio = |to: f32, from: f32| if from != 0.0 { to / from } else { 1.0 };
+        self.origin = self.rect.set_scale_with_origin(
+            ratio(scale.x, self.scale.x),
+            ratio(scale.y, self.scale.y),
+            self.origin,
+        );
+        self.scale = scale;
+    }
+
+    fn set_rotation(&mut self, rad: f32) {
+        self.origin = self.rect.set_rotation_with_origin(rad, self.origin);
+        if let Some((rect, origin, _)) = &mut self.collapsed {
+            *origin = rect.set_rotation_with_origin(rad, *origin);
+        }
+        self.rotation += rad;
+    }
+
+    fn transform(&self) -> SpriteTransform {
+        SpriteTransform {
+            rect: self.rect,
+            origin: self.origin,
+            scale: self.scale,
+            rotation: self.rotation,
+            collapsed: self.collapsed,
+        }
+    }
+
+    fn set_transform(&mut self, transform: SpriteTransform) {
+        self.rect = transform.rect;
+        self.origin = transform.origin;
+        self.scale = transform.scale;
+        self.rotation = transform.rotation;
+        self.collapsed = transform.collapsed;
+    }
+}
+
+// what SpriteData::transform saves to put back
+#[derive(Clone, Copy)]
+struct SpriteTransform {
+    rect: Rect,
+    origin: Origin,
+    scale: Vector2d,
+    rotation: f32,
+    collapsed: Option<(Rect, Origin, Vector2d)>,
 }
 
 enum SpriteDataPoint {
-    TopLeft(SpriteData),
+    // boxed so the bottom right points don't take up a whole sprite each
+    TopLeft(Box<SpriteData>),
     BottomRight,
 }
 
+// a run of quads that share the same draw state
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct DrawBatch {
+    start: u32,
+    len: u32,
+    // index into the masks, the quads are only drawn where the mask has been drawn into the
+    // stencil buffer first, NO_MASK for none
+    mask: u32,
+    // ATLAS_TEXTURE or the id of a render target
+    texture: u32,
+    // index into the text styles, for distance field glyphs, NO_STYLE for plain quads
+    style: u32,
+}
+
+const NO_MASK: u32 = !0;
+const NO_STYLE: u32 = !0;
+
 struct RenderData {
     // index buffer
     index: Vec<u16>,
     // vert attributes, since each vert has diff pos/uv so it's best to use vert attrib
     sprite_pos: Vec<Rect>,
     tex_pos: Vec<Frame>,
+    // one color per vert, same order as the rect
+    color: Vec<[Color; 4]>,
+    batches: Vec<DrawBatch>,
+    masks: Vec<Rect>,
+    text_styles: Vec<TextStyle>,
+    // offscreen passes in the order they need to be drawn, the screen ones come last
+    passes: Vec<RenderPass>,
+    // from PixelSnap
+    uv_inset: f32,
+    // quads that move together when snapped, like all the quads of one sprite, in order
+    snap_blocks: Vec<Range<usize>>,
+    // a triangle list, swapped in whole by every update instead of cleared
+    debug: DebugDraw,
 }
 
 impl RenderData {
@@ -606,7 +1210,15 @@ impl RenderData {
         Self {
             sprite_pos: Vec::with_capacity(64),
             tex_pos: Vec::with_capacity(64),
+            color: Vec::with_capacity(64),
+            batches: Vec::with_capacity(8),
+            masks: Vec::with_capacity(8),
+            text_styles: Vec::with_capacity(4),
+            passes: Vec::with_capacity(4),
             index: vec![0, 1, 3, 1, 2, 3],
+            uv_inset: 0.0,
+            snap_blocks: Vec::with_capacity(64),
+            debug: DebugDraw::default(),
         }
     }
 
@@ -614,9 +1226,155 @@ impl RenderData {
         self.sprite_pos.push(sprite.rect);
 
         self.tex_pos.push(sprite.frame + offset);
+
+        self.color.push([sprite.tint; 4]);
+    }
+
+    fn load_raw(&mut self, rect: Rect, frame: Frame, tint: Color) {
+        self.sprite_pos.push(rect);
+        self.tex_pos.push(frame);
+        self.color.push([tint; 4]);
+    }
+
+    // the clip has to be axis aligned, and so does the sprite for it to be cut
+    fn load_clipped(&mut self, rect: Rect, frame: Frame, tint: Color, clip: Option<&Rect>) {
+        match clip {
+            None => self.load_raw(rect, frame, tint),
+            Some(clip) => {
+                if let Some((rect, frame)) = clip_quad(&rect, &frame, clip) {
+                    self.load_raw(rect, frame, tint);
+                }
+            }
+        }
     }
 
-    fn ensure_index_len(&mut self, size: usize) {
+    // `offset` moves the quads after clipping, for parallax layers and repeated copies
+    fn load_sprite(
+        &mut self,
+        sprite: &SpriteData,
+        time: f32,
+        clip: Option<&Rect>,
+        offset: Vector2d,
+    ) {
+        let start = self.sprite_pos.len();
+        // the clip is where the sprite ends up, so it gets moved back onto the sprite as it is
+        let moved_clip = clip.map(|rect| {
+            let mut rect = *rect;
+            rect.offset_pos(-offset);
+            rect
+        });
+        self.load_sprite_in_place(sprite, time, moved_clip.as_ref());
+        // render targets don't have texels to bleed from
+        if self.uv_inset > 0.0 && sprite.texture.is_none() {
+            self.inset_uvs(start, sprite);
+        }
+        if offset.x != 0.0 || offset.y != 0.0 {
+            for rect in &mut self.sprite_pos[start..] {
+                rect.offset_pos(offset);
+            }
+        }
+        self.snap_blocks.push(start..self.sprite_pos.len());
+    }
+
+    fn load_sprite_in_place(&mut self, sprite: &SpriteData, time: f32, clip: Option<&Rect>) {
+        // flipping only swaps the uv corners, the rect and its origin stay where they are
+        let (flip_x, flip_y) = (sprite.flip_x, sprite.flip_y);
+        let frame = sprite.frame.flipped(flip_x, flip_y, false);
+        // nine slicing takes over from the stencil, a scrolling border makes no sense
+        if let Some(slice) = &sprite.nine_slice {
+            for_each_slice(
+                &sprite.rect,
+                &frame,
+                sprite.native_size,
+                &slice.flipped(flip_x, flip_y),
+                |rect, frame| self.load_clipped(rect, frame, sprite.tint, clip),
+            );
+            return;
+        }
+        match &sprite.stencil {
+            None if clip.is_none() && !flip_x && !flip_y => self.load(sprite, FrameVector2d::Zero),
+            None => self.load_clipped(sprite.rect, frame, sprite.tint, clip),
+            Some(stencil) => match stencil.scroll(&sprite.frame, time) {
+                Some(scroll) if clip.is_none() && !flip_x && !flip_y => self.load(sprite, scroll),
+                _ => {
+                    for (rect, piece) in stencil.pieces(&sprite.rect, time) {
+                        let rect = if flip_x || flip_y {
+                            mirror_piece(&sprite.rect, &rect, flip_x, flip_y)
+                        } else {
+                            rect
+                        };
+                        let piece = piece.flipped(flip_x, flip_y, false);
+                        self.load_clipped(rect, piece, sprite.tint, clip);
+                    }
+                }
+            },
+        }
+    }
+
+    // closes off everything loaded since the last batch
+    fn end_batch(&mut self, mask: Option<Rect>, texture: u32) {
+        let start = self
+            .batches
+            .last()
+            .map(|batch| batch.start + batch.len)
+            .unwrap_or(0);
+        let len = self.sprite_pos.len() as u32 - start;
+        if len == 0 {
+            return;
+        }
+        let mask = match mask {
+            Some(rect) => {
+                self.masks.push(rect);
+                self.masks.len() as u32 - 1
+            }
+            None => NO_MASK,
+        };
+        self.batches.push(DrawBatch {
+            start,
+            len,
+            mask,
+            texture,
+            style: NO_STYLE,
+        });
+    }
+
+    // same as end_batch but the quads are distance field glyphs out of the atlas
+    fn end_styled_batch(&mut self, style: TextStyle) {
+        let batch_len = self.batches.len();
+        self.end_batch(None, ATLAS_TEXTURE);
+        if self.batches.len() > batch_len {
+            self.text_styles.push(style);
+            self.batches.last_mut().unwrap().style = self.text_styles.len() as u32 - 1;
+        }
+    }
+
+    // closes off every batch since the last pass, passes get reordered so the last one in the vec
+    // isn't necessarily the last one loaded
+    fn end_pass(&mut self, target: u32, width: u32, height: u32, view: &Rect, viewport: [f32; 4]) {
+        let batch_start = self
+            .passes
+            .iter()
+            .map(|pass| pass.batch_start + pass.batch_len)
+            .max()
+            .unwrap_or(0);
+        self.passes.push(RenderPass {
+            target,
+            width,
+            height,
+            batch_start,
+            batch_len: self.batches.len() as u32 - batch_start,
+            view: [
+                view.top_left().x,
+                view.top_left().y,
+                view.width(),
+                view.height(),
+            ],
+            viewport,
+        });
+    }
+
+    // returns how many times it had to grow
+    fn ensure_index_len(&mut self, size: usize) -> u32 {
         if self.index.len() < size {
             self.index.reserve(self.index.len());
             unsafe {
@@ -629,13 +1387,20 @@ impl RenderData {
                 self.index[index] += (self.index.len() / 3) as u16;
             }
             // log_str(&format!("{:?}, len is {:?}", self.index, self.index.len()));
-            self.ensure_index_len(size);
+            return self.ensure_index_len(size) + 1;
         }
+        0
     }
 
     fn clear(&mut self) {
         self.sprite_pos.clear();
         self.tex_pos.clear();
+        self.color.clear();
+        self.batches.clear();
+        self.masks.clear();
+        self.text_styles.clear();
+        self.passes.clear();
+        self.snap_blocks.clear();
         // we leave index array as is
     }
 }
@@ -647,6 +1412,62 @@ struct Scene {
     part_map: PartitionMap<SpriteDataPoint>,
     tex_atlas: TextureAtlas,
     anim_seq: SeqTable,
+    // added with add_sequence, every sprite, tile and emitter steps its own playback of them
+    sequences: Sequences,
+    // where the two points of each sprite currently live in the part map
+    sprite_keys: HashMap<SpriteId, (Key, Key)>,
+    next_sprite_id: u32,
+    tweens: TweenTable,
+    // seconds of delta_t fed into update so far
+    time: f32,
+    clips: HashMap<ClipId, Clip>,
+    next_clip_id: u32,
+    render_targets: HashMap<RenderTargetId, RenderTarget>,
+    next_render_target_id: u32,
+    tilemaps: HashMap<TilemapId, Tilemap>,
+    next_tilemap_id: u32,
+    fonts: HashMap<FontId, Font>,
+    next_font_id: u32,
+    texts: HashMap<TextId, TextData>,
+    next_text_id: u32,
+    emitters: HashMap<EmitterId, Emitter>,
+    next_emitter_id: u32,
+    // the atlas only has uvs, the insets of its entries are kept here and handed out together
+    // with the frame by atlas_entry
+    slice_insets: HashMap<String, NineSlice>,
+    // what the screen showed as of the last update, for picking
+    last_camera: Camera,
+    // None if it's the same size as the camera
+    screen_size: Option<Vector2d>,
+    // the biggest bounding rect of any sprite so far, how far back from a point the sprites
+    // overlapping it can be in the part map
+    max_sprite_extent: Vector2d,
+    hit_masks: HashMap<HitMaskId, HitMask>,
+    next_hit_mask_id: u32,
+    pointer: PointerState,
+    ui: Ui,
+    camera_controller: Option<CameraController>,
+    // layers the main camera sees
+    screen_layers: LayerMask,
+    viewports: HashMap<ViewportId, Viewport>,
+    next_viewport_id: u32,
+    parallax: Parallax,
+    tags: Tags,
+    groups: Groups,
+    visibility: Visibility,
+    // atlas entries packed a quarter turn clockwise
+    rotated_frames: HashSet<String>,
+    pixel_snap: Option<PixelSnap>,
+    // what's been drawn since the last update
+    debug: DebugDraw,
+    culling_overlay: bool,
+    overlay_regions: Vec<(u32, u32, u32)>,
+    // prefab roots to the rest of their sprites
+    attached: HashMap<SpriteId, Vec<SpriteId>>,
+    // sprites with a sequence playing
+    animated: HashSet<SpriteId>,
+    // filled in by every update
+    stats: FrameStats,
 }
 
 impl Scene {
@@ -657,77 +1478,427 @@ impl Scene {
             vert_attrib: RenderData::new(),
             part_map: PartitionMap::new(size, depth),
             anim_seq: SeqTable::new(),
+            sequences: Sequences::default(),
+            sprite_keys: HashMap::new(),
+            next_sprite_id: 0,
+            tweens: TweenTable::new(),
+            time: 0.0,
+            clips: HashMap::new(),
+            next_clip_id: 0,
+            render_targets: HashMap::new(),
+            next_render_target_id: 0,
+            tilemaps: HashMap::new(),
+            next_tilemap_id: 0,
+            fonts: HashMap::new(),
+            next_font_id: 0,
+            texts: HashMap::new(),
+            next_text_id: 0,
+            emitters: HashMap::new(),
+            next_emitter_id: 0,
+            slice_insets: HashMap::new(),
+            last_camera: Camera::new(0.0, 0.0, size as f32, size as f32),
+            screen_size: None,
+            max_sprite_extent: Vector2d { x: 0.0, y: 0.0 },
+            hit_masks: HashMap::new(),
+            next_hit_mask_id: 0,
+            pointer: PointerState::default(),
+            ui: Ui::new(),
+            camera_controller: None,
+            screen_layers: LayerMask::all(),
+            viewports: HashMap::new(),
+            next_viewport_id: 0,
+            parallax: Parallax::default(),
+            tags: Tags::default(),
+            groups: Groups::default(),
+            visibility: Visibility::new(),
+            rotated_frames: HashSet::new(),
+            pixel_snap: None,
+            debug: DebugDraw::default(),
+            culling_overlay: false,
+            overlay_regions: vec![],
+            attached: HashMap::new(),
+            animated: HashSet::new(),
+            stats: FrameStats::default(),
         }
     }
 
     // ok but how does the coord system works
     fn add_sprite(&mut self, pos: Vector2d, texture: &str) -> Result<Sprite, &'static str> {
-        let (tex, mut rect) = self.tex_atlas.get(texture)?;
+        let id = self.add_sprite_raw(pos, texture)?;
+        Ok(Sprite { scene: self, id })
+    }
+
+    // for sprites owned by the scene itself, which get removed with remove_sprite_raw
+    fn add_sprite_raw(&mut self, pos: Vector2d, texture: &str) -> Result<SpriteId, &'static str> {
+        let entry = self.atlas_entry(texture)?;
+        let mut rect = entry.frame_rect;
         rect.set_pos_top_left(pos.x, pos.y);
 
-        let tl_data = SpriteDataPoint::TopLeft(SpriteData {
-            rect,
-            frame: tex,
-            anim_key: None,
-        });
+        let id = SpriteId(self.next_sprite_id);
+        self.next_sprite_id += 1;
+
+        let mut data = SpriteData::new(id, rect, entry.frame, &entry.frame_rect);
+        data.nine_slice = entry.nine_slice;
+        data.frame_name = Some(texture.to_string());
+        let tl_data = SpriteDataPoint::TopLeft(Box::new(data));
         let br_data = SpriteDataPoint::BottomRight;
 
         // basically after inserting this point the pos data is lost since it's griddified already
         let tl_key = self.part_map.insert_point(pos, tl_data)?;
         let br_key = self.part_map.insert_point(pos, br_data)?;
+        self.sprite_keys.insert(id, (tl_key, br_key));
+        self.grow_sprite_extent(&rect);
 
         // you don't need to get bounding box since there's no rotation going on here
-        let sprite = Sprite {
-            scene: self,
-            key_top_left: Some(tl_key),
-            key_bottom_right: Some(br_key),
-            origin: Origin::zero(),
-        };
-        Ok(sprite)
+        Ok(id)
     }
 
-    fn remove_sprite_raw(
-        &mut self,
-        id_topleft: Key,
-        id_bottomright: Key,
-    ) -> Result<(), &'static str> {
+    fn remove_sprite_raw(&mut self, id: SpriteId) -> Result<(), &'static str> {
+        let data = self.sprite_data_mut(id)?;
+        let (tags, groups) = (
+            std::mem::take(&mut data.tags),
+            std::mem::take(&mut data.groups),
+        );
+        let (id_topleft, id_bottomright) =
+            self.sprite_keys.remove(&id).ok_or("invalid sprite id")?;
         self.part_map.remove_point(id_topleft)?;
         self.part_map.remove_point(id_bottomright)?;
+        self.tweens.remove_sprite(id);
+        self.animated.remove(&id);
+        self.forget_pointer_target(id);
+        self.parallax.repeating.remove(&id);
+        self.forget_tags(id, &tags);
+        self.forget_groups(id, &groups);
+        // the rest of a prefab goes with its root
+        if let Some(attached) = self.attached.remove(&id) {
+            for child in attached {
+                // some might've been removed on their own already
+                self.remove_sprite_raw(child).ok();
+            }
+        }
+        Ok(())
+    }
+
+    fn sprite_data(&self, id: SpriteId) -> Result<&SpriteData, &'static str> {
+        let (tl_key, _) = self.sprite_keys.get(&id).ok_or("invalid sprite id")?;
+        match &self.part_map[tl_key] {
+            SpriteDataPoint::TopLeft(data) => Ok(data),
+            SpriteDataPoint::BottomRight => Err("shouldn't be br point"),
+        }
+    }
+
+    fn sprite_data_mut(&mut self, id: SpriteId) -> Result<&mut SpriteData, &'static str> {
+        let (tl_key, _) = self.sprite_keys.get(&id).ok_or("invalid sprite id")?;
+        match &mut self.part_map[tl_key] {
+            SpriteDataPoint::TopLeft(data) => Ok(data),
+            SpriteDataPoint::BottomRight => Err("shouldn't be br point"),
+        }
+    }
+
+    // needs to be called every time the rect of a sprite changes
+    fn update_keys(&mut self, id: SpriteId) -> Result<(), &'static str> {
+        let bound_rect = self.sprite_data(id)?.rect.get_bounding_rect();
+        // both are checked before either moves, so a failure leaves the keys as they were
+        self.part_map.point_to_region(bound_rect.top_left())?;
+        self.part_map.point_to_region(bound_rect.bottom_right())?;
+        let (tl_key, br_key) = self.sprite_keys.remove(&id).ok_or("invalid sprite id")?;
+        let tl_key = self.part_map.move_point(tl_key, bound_rect.top_left())?;
+        let br_key = self
+            .part_map
+            .move_point(br_key, bound_rect.bottom_right())?;
+        self.sprite_keys.insert(id, (tl_key, br_key));
+        self.grow_sprite_extent(&bound_rect);
         Ok(())
     }
 
+    // replaces whatever the sprite was playing
+    fn play_sprite(&mut self, id: SpriteId, seq: &str) -> Result<(), &'static str> {
+        self.play_sprite_at(id, seq, None)
+    }
+
+    // same as play_sprite but starts at a frame index and the seconds already spent on it
+    fn play_sprite_at(
+        &mut self,
+        id: SpriteId,
+        seq: &str,
+        position: Option<(usize, f32)>,
+    ) -> Result<(), &'static str> {
+        let playback = self.sequences.start(seq, position)?;
+        let data = self.sprite_data_mut(id)?;
+        data.playback = Some(playback);
+        data.playing = Some(seq.to_string());
+        self.animated.insert(id);
+        Ok(())
+    }
+
+    // every playing sprite that isn't paused steps its sequence and takes on the current frame,
+    // sized by its sizing policy
+    fn update_animations(&mut self, delta_t: f32) {
+        let animated = std::mem::take(&mut self.animated);
+        let mut stepped = 0;
+        for id in &animated {
+            let (mut playback, step) = match self.sprite_data(*id) {
+                Ok(data) => match data.playback {
+                    Some(playback) => (playback, data.anim_step(delta_t)),
+                    None => continue,
+                },
+                Err(_) => continue,
+            };
+            if let Some(step) = step {
+                self.sequences.step(&mut playback, step);
+                stepped += 1;
+            }
+            let (frame, frame_rect) = self.sequences.frame(&playback);
+            let is_resized = match self.sprite_data_mut(*id) {
+                Ok(data) => {
+                    data.playback = Some(playback);
+                    data.animate(frame, &frame_rect)
+                }
+                Err(_) => continue,
+            };
+            if is_resized {
+                // a frame that grows the sprite off the map leaves it where it was in the part map
+                self.update_keys(*id).ok();
+            }
+        }
+        self.animated = animated;
+        self.stats.animated = stepped;
+    }
+
+    // puts the transform back when the sprite would end up off the map
+    fn transform_sprite(
+        &mut self,
+        id: SpriteId,
+        change: impl FnOnce(&mut SpriteData),
+    ) -> Result<(), &'static str> {
+        let data = self.sprite_data_mut(id)?;
+        let before = data.transform();
+        change(data);
+        if let Err(err) = self.update_keys(id) {
+            self.sprite_data_mut(id)?.set_transform(before);
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    // never shrinks, it's only there to bound the search
+    fn grow_sprite_extent(&mut self, rect: &Rect) {
+        let bound = rect.get_bounding_rect();
+        self.max_sprite_extent.x = self.max_sprite_extent.x.max(bound.width());
+        self.max_sprite_extent.y = self.max_sprite_extent.y.max(bound.height());
+    }
+
+    /// tweens a property of the sprite from whatever it is when the tween starts to `target`
+    fn tween(
+        &mut self,
+        sprite: &Sprite,
+        property: TweenProperty,
+        target: TweenValue,
+        duration: f32,
+        easing: Easing,
+    ) -> Result<TweenId, &'static str> {
+        let tween = Tween::new(sprite.id, property, target, duration, easing)?;
+        Ok(self.tweens.add(TweenGroup::Single(tween)))
+    }
+
+    /// for sequences and parallel groups, see `TweenGroup`
+    fn tween_group(&mut self, group: TweenGroup) -> TweenId {
+        self.tweens.add(group)
+    }
+
+    fn cancel_tween(&mut self, id: TweenId) -> Result<(), &'static str> {
+        self.tweens.cancel(id)
+    }
+
+    /// ids of the tweens that ran to completion since the last call
+    fn finished_tweens(&mut self) -> std::vec::Drain<'_, TweenId> {
+        self.tweens.finished.drain(..)
+    }
+
+    /// ids of the tweens that were dropped since the last call and why, a tween that would move
+    /// its sprite off the map leaves it where the last step put it
+    fn failed_tweens(&mut self) -> std::vec::Drain<'_, (TweenId, &'static str)> {
+        self.tweens.failed.drain(..)
+    }
+
     fn update(&mut self, cam: &Camera, delta_t: f32) -> WasmVertAttribPtr {
         // todo, do cam matrix mult
+        let begin = Stopwatch::start();
+        let mut phase = begin;
+        self.stats = FrameStats {
+            sprites: self.sprite_keys.len() as u32,
+            ..FrameStats::default()
+        };
+        self.time += delta_t;
         self.anim_seq.update(delta_t);
+        self.update_animations(delta_t);
+        self.update_tilemaps(delta_t);
+        self.stats.anim_ms = phase.lap();
+        self.update_tweens(delta_t);
+        self.stats.tween_ms = phase.lap();
+        self.update_particles(delta_t);
+        self.stats.particle_ms = phase.lap();
+        // after everything that moves sprites, so the camera isn't a frame behind its target
+        let cam = self.update_camera(cam, delta_t);
+        let cam = self.snap_camera(cam);
+        self.last_camera = cam;
+        self.update_ui();
+        self.stats.camera_ms = phase.lap();
 
         self.vert_attrib.clear();
+        self.flush_debug_draw();
+
+        // offscreen passes first, put in dependency order once it's known what they sample
+        let mut targets: Vec<RenderTargetId> = self
+            .render_targets
+            .iter()
+            .filter(|(_, target)| target.needs_pass())
+            .map(|(id, _)| *id)
+            .collect();
+        targets.sort();
+        let mut samples = Vec::with_capacity(targets.len());
+        for id in targets {
+            let target = &self.render_targets[&id];
+            let (view, width, height) = (target.camera.rect, target.width, target.height);
+            let filter = PassFilter::Target(id, target.layers);
+            let pixels = Vector2d {
+                x: width as f32,
+                y: height as f32,
+            };
+            samples.push(self.emit_pass(&view, filter, pixels));
+            self.vert_attrib
+                .end_pass(id.0, width, height, &view, FULL_VIEWPORT);
+            self.render_target_mut(id).unwrap().dirty = false;
+        }
+        sort_passes(&mut self.vert_attrib.passes, &samples);
 
         // cam occlusion
-        let regions = self
-            .part_map
-            .points_to_regions(cam.rect.top_left(), cam.rect.bottom_right())
-            .unwrap();
-        for reg in regions {
-            let vec = self.part_map.raw_map.get(&reg).unwrap();
-            for each in vec {
-                if each.is_some() {
-                    match each.as_ref().unwrap() {
-                        // todo, fix this with real frame vec
-                        // todo, maybe simd the loading??
-                        SpriteDataPoint::TopLeft(data) => {
-                            self.vert_attrib.load(data, FrameVector2d::Zero)
-                        }
-                        SpriteDataPoint::BottomRight => {}
-                    }
-                }
-            }
+        let screen = self.screen_extent();
+        self.emit_pass(&cam.rect, PassFilter::Screen(self.screen_layers), screen);
+        self.vert_attrib
+            .end_pass(SCREEN_TARGET, 0, 0, &cam.rect, FULL_VIEWPORT);
+        self.emit_viewport_passes();
+        if self.culling_overlay {
+            self.emit_culling_overlay(&cam.rect, PassFilter::Screen(self.screen_layers));
+        }
+        // the ui goes over every camera, once
+        if !self.ui.is_empty() {
+            let view = self.ui_view();
+            self.emit_pass(&view, PassFilter::Ui, screen);
+            self.vert_attrib
+                .end_pass(SCREEN_TARGET, 0, 0, &view, FULL_VIEWPORT);
         }
 
         // finish
-        self.vert_attrib
+        self.stats.index_allocations = self
+            .vert_attrib
             .ensure_index_len(self.vert_attrib.sprite_pos.len());
+        self.stats.pass_ms = phase.lap();
+        self.stats.quads = self.vert_attrib.sprite_pos.len() as u32;
+        self.stats.batches = self.vert_attrib.batches.len() as u32;
+        self.stats.passes = self.vert_attrib.passes.len() as u32;
+        self.stats.index_len = self.vert_attrib.index.len() as u32;
+        self.stats.total_ms = begin.elapsed_ms();
+
+        WasmVertAttribPtr::new(&self.vert_attrib, &self.stats)
+    }
+
+    /// culls everything `view` can see into batches, returns the render targets sampled
+    // `pixels` is the size of what the pass gets drawn onto, for pixel snapping
+    fn emit_pass(&mut self, view: &Rect, filter: PassFilter, pixels: Vector2d) -> Vec<u32> {
+        let start = self.vert_attrib.sprite_pos.len();
+        // tilemaps cull themselves by chunk and go into the atlas batch ahead of the sprites
+        for tilemap in self.tilemaps.values_mut() {
+            let depth = tilemap.depth();
+            if filter.accepts_layer(depth) && self.visibility.shows_layer(depth) {
+                tilemap.load(&mut self.vert_attrib, &self.sequences, view);
+            }
+        }
+        // same for particles, culled by emitter
+        for emitter in self.emitters.values() {
+            let depth = emitter.depth();
+            if filter.accepts_layer(depth) && self.visibility.shows_layer(depth) {
+                emitter.load(&mut self.vert_attrib, &self.sequences, view);
+            }
+        }
+        // text isn't in the part map, each one is culled by the bounds of its glyphs
+        let bound = view.get_bounding_rect();
+        // distance field text needs a batch per style, those go after the plain quads. texts that
+        // share a style share the batch
+        let mut styled: Vec<(TextStyle, Vec<&TextData>)> = vec![];
+        for text in self.texts.values() {
+            if !filter.accepts_text(text) || !self.visibility.shows_layer(text.depth()) {
+                continue;
+            }
+            match text.style() {
+                None => text.load(&mut self.vert_attrib, &bound, &self.clips),
+                Some(style) => match styled.iter_mut().find(|(each, _)| *each == style) {
+                    Some((_, texts)) => texts.push(text),
+                    None => styled.push((style, vec![text])),
+                },
+            }
+        }
 
-        WasmVertAttribPtr::new(&self.vert_attrib)
+        // parallax layers and repeated copies come with how far they need to be moved
+        let query = SpriteQuery {
+            part_map: &self.part_map,
+            sprite_keys: &self.sprite_keys,
+            max_sprite_extent: self.max_sprite_extent,
+            visibility: &self.visibility,
+            view,
+            filter,
+        };
+        let visible = self.parallax.visible_sprites(query, &mut self.stats);
+        // sprites that need a batch of their own, either sampling a render target or clipped
+        // with the stencil buffer
+        let mut deferred: Vec<(u32, Option<ClipId>, &SpriteData, Vector2d)> = vec![];
+        for (data, offset) in visible {
+            let texture = match data.texture {
+                None => ATLAS_TEXTURE,
+                Some(id) if self.render_targets.contains_key(&id) => id.0,
+                // the target it samples is gone
+                Some(_) => {
+                    self.stats.culled += 1;
+                    continue;
+                }
+            };
+            self.stats.emitted += 1;
+            let (cut, mask) = resolve_clip(&self.clips, data);
+            if texture == ATLAS_TEXTURE && mask.is_none() {
+                self.vert_attrib.load_sprite(data, self.time, cut, offset);
+            } else {
+                deferred.push((texture, mask, data, offset));
+            }
+        }
+        self.vert_attrib.end_batch(None, ATLAS_TEXTURE);
+
+        for (style, texts) in styled {
+            for text in texts {
+                text.load(&mut self.vert_attrib, &bound, &self.clips);
+            }
+            self.vert_attrib.end_styled_batch(style);
+        }
+
+        let mut samples = vec![];
+        deferred.sort_by_key(|(texture, mask, _, _)| (*texture, *mask));
+        for (index, (texture, mask, data, offset)) in deferred.iter().enumerate() {
+            let (cut, _) = resolve_clip(&self.clips, data);
+            self.vert_attrib.load_sprite(data, self.time, cut, *offset);
+            let next = deferred
+                .get(index + 1)
+                .map(|(texture, mask, _, _)| (*texture, *mask));
+            if next != Some((*texture, *mask)) {
+                let mask = mask.map(|id| self.clips[&id].rect);
+                self.vert_attrib.end_batch(mask, *texture);
+                if *texture != ATLAS_TEXTURE && !samples.contains(texture) {
+                    samples.push(*texture);
+                }
+            }
+        }
+        if self.pixel_snap.is_some() {
+            self.vert_attrib.snap_quads(start, view, pixels);
+        }
+        samples
     }
 
     // ui and world can each only have one layer with ysort enabled
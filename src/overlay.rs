@@ -0,0 +1,102 @@
+use crate::*;
+
+// how many sprites a region needs to show up fully tinted
+const FULL_REGION: f32 = 16.0;
+
+impl Scene {
+    /// draws how the main camera got culled into the debug batch every update: the rects the
+    /// world and every parallax layer got looked up with, the regions those looked in, shaded by
+    /// how many sprites they hold, the view, and the bounding rect of every sprite found, green
+    /// when it got drawn and red when it got skipped for being outside the view or for its
+    /// layer, target or visibility.
+    /// repeating sprites are drawn at every copy that covers the view
+    pub(crate) fn set_culling_overlay(&mut self, is_enabled: bool) {
+        self.culling_overlay = is_enabled;
+    }
+
+    /// (x, y, sprites) for every region the last overlay looked in
+    pub(crate) fn overlay_regions(&self) -> &[(u32, u32, u32)] {
+        &self.overlay_regions
+    }
+
+    pub(crate) fn emit_culling_overlay(&mut self, view: &Rect, filter: PassFilter) {
+        let bound = view.get_bounding_rect();
+        // lines a screen pixel wide whatever the zoom
+        let line = bound.width() / self.screen_extent().x.max(1.0);
+        let drawn = Color::new(0.2, 1.0, 0.2, 0.8);
+        let skipped = Color::new(1.0, 0.2, 0.2, 0.8);
+
+        let debug = &mut self.vert_attrib.debug;
+        let div_size = self.part_map.div_size;
+        self.overlay_regions.clear();
+        // the same lookups visible_sprites makes, parallax layers can look in the same region
+        // as the world does
+        let mut shaded = HashSet::new();
+        for (layer, offset, lookup) in self.parallax.lookups(&bound, self.max_sprite_extent) {
+            let regions = self
+                .part_map
+                .points_to_regions(lookup.top_left(), lookup.bottom_right())
+                .unwrap();
+            for region in regions {
+                let Region(x, y) = region;
+                let points = self.part_map.raw_map.get(&region);
+                let sprites: Vec<&SpriteData> = points
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                    .filter_map(|point| match point {
+                        SpriteDataPoint::TopLeft(data) => Some(data.as_ref()),
+                        SpriteDataPoint::BottomRight => None,
+                    })
+                    .collect();
+                if shaded.insert((x, y)) {
+                    let cell = Rect::new_raw(
+                        x as f32 * div_size,
+                        y as f32 * div_size,
+                        0.0,
+                        div_size,
+                        div_size,
+                    );
+                    let count = sprites.len() as u32;
+                    let shade = (count as f32 / FULL_REGION).min(1.0) * 0.4;
+                    if shade > 0.0 {
+                        debug.rect(&cell, Color::new(0.2, 0.4, 1.0, shade), 0.0);
+                    }
+                    debug.rect(&cell, Color::new(1.0, 1.0, 1.0, 0.3), line);
+                    self.overlay_regions.push((x, y, count));
+                }
+                // only the sprites this lookup is the one for, repeating ones come after
+                for data in sprites {
+                    if !self.parallax.is_looked_up(layer, data) || data.repeat.any() {
+                        continue;
+                    }
+                    let is_drawn = is_shown(data, offset, &bound, filter, &self.visibility);
+                    let color = if is_drawn { drawn } else { skipped };
+                    // where it's drawn, same as is_shown checks
+                    let mut rect = data.rect.get_bounding_rect();
+                    rect.offset_pos(offset);
+                    debug.rect(&rect, color, line);
+                }
+            }
+            debug.rect(&lookup, Color::new(1.0, 0.9, 0.1, 0.9), line * 2.0);
+        }
+
+        for id in self.parallax.repeating.iter() {
+            let data = match self.sprite_keys.get(id).map(|(key, _)| &self.part_map[key]) {
+                Some(SpriteDataPoint::TopLeft(data)) => data,
+                _ => continue,
+            };
+            let rect = data.rect.get_bounding_rect();
+            if !filter.accepts(data) || !self.visibility.shows(data) {
+                debug.rect(&rect, skipped, line);
+                continue;
+            }
+            for copy in self.parallax.repeat_copies(data, &bound) {
+                let mut rect = rect;
+                rect.offset_pos(copy);
+                debug.rect(&rect, drawn, line);
+            }
+        }
+        debug.rect(view, Color::new(0.1, 0.9, 1.0, 0.9), line * 2.0);
+    }
+}
@@ -0,0 +1,288 @@
+use crate::*;
+
+/// which ways a sprite repeats forever, the copies are laid edge to edge along its bounding rect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub(crate) struct Repeat {
+    pub(crate) x: bool,
+    pub(crate) y: bool,
+}
+
+impl Repeat {
+    pub(crate) fn any(&self) -> bool {
+        self.x || self.y
+    }
+}
+
+// a single view shouldn't ever need more copies than this along one axis
+const MAX_REPEAT: i32 = 256;
+
+// which copies of a span along one axis overlap the view, just the span itself when it doesn't
+// repeat
+fn repeat_range(start: f32, len: f32, view_start: f32, view_end: f32, repeats: bool) -> (i32, i32) {
+    if !repeats || len <= 0.0 {
+        return (0, 0);
+    }
+    let first = ((view_start - start) / len).floor() as i32;
+    let last = ((view_end - start) / len).ceil() as i32 - 1;
+    (first, last.min(first + MAX_REPEAT))
+}
+
+// world layers sit at layer + 128 like Sprite::set_layer puts them, which doesn't fit in a u8
+fn layer_key(depth: f32) -> u16 {
+    depth as u16
+}
+
+/// what Parallax::visible_sprites looks through, and for which view and pass
+pub(crate) struct SpriteQuery<'a> {
+    pub(crate) part_map: &'a PartitionMap<SpriteDataPoint>,
+    pub(crate) sprite_keys: &'a HashMap<SpriteId, (Key, Key)>,
+    pub(crate) max_sprite_extent: Vector2d,
+    pub(crate) visibility: &'a Visibility,
+    pub(crate) view: &'a Rect,
+    pub(crate) filter: PassFilter,
+}
+
+#[derive(Default)]
+pub(crate) struct Parallax {
+    // per layer depth, how much of the camera movement the layer follows
+    factors: HashMap<u16, Vector2d>,
+    // sprites with a repeat set, they aren't culled through the part map
+    pub(crate) repeating: HashSet<SpriteId>,
+}
+
+impl Parallax {
+    // how far the sprites on a layer get moved for the view
+    fn offset(&self, depth: f32, view: &Rect) -> Vector2d {
+        let factor = match self.factors.get(&layer_key(depth)) {
+            Some(factor) => *factor,
+            None => return Vector2d { x: 0.0, y: 0.0 },
+        };
+        let top_left = view.top_left();
+        Vector2d {
+            x: top_left.x * (1.0 - factor.x),
+            y: top_left.y * (1.0 - factor.y),
+        }
+    }
+
+    /// the world, then every parallax layer where the view lands for it, as the layer (None for
+    /// everything that isn't on a parallax layer), how far its sprites get moved and the rect
+    /// looked up in the part map
+    pub(crate) fn lookups(
+        &self,
+        bound: &Rect,
+        max_sprite_extent: Vector2d,
+    ) -> Vec<(Option<u16>, Vector2d, Rect)> {
+        let no_offset = Vector2d { x: 0.0, y: 0.0 };
+        let mut lookups = vec![(None, no_offset)];
+        for depth in self.factors.keys() {
+            lookups.push((Some(*depth), self.offset(*depth as f32, bound)));
+        }
+        lookups
+            .into_iter()
+            .map(|(layer, offset)| {
+                // sprites are kept at their top left, so ones overlapping the view can start
+                // before it
+                let rect = Rect::from_two_points(
+                    bound.top_left() - offset - max_sprite_extent,
+                    bound.bottom_right() - offset,
+                    0.0,
+                );
+                (layer, offset, rect)
+            })
+            .collect()
+    }
+
+    // whether the lookup for `layer` is the one that finds the sprite
+    pub(crate) fn is_looked_up(&self, layer: Option<u16>, data: &SpriteData) -> bool {
+        let depth = layer_key(data.rect.top_left.2);
+        match layer {
+            None => !self.factors.contains_key(&depth),
+            Some(layer) => layer == depth,
+        }
+    }
+
+    /// how far every copy of a repeating sprite that overlaps the view gets moved
+    pub(crate) fn repeat_copies(&self, data: &SpriteData, bound: &Rect) -> Vec<Vector2d> {
+        let offset = self.offset(data.rect.top_left.2, bound);
+        let rect = data.rect.get_bounding_rect();
+        let (start, end) = (rect.top_left() + offset, rect.bottom_right() + offset);
+        let (width, height) = (end.x - start.x, end.y - start.y);
+        let (view_start, view_end) = (bound.top_left(), bound.bottom_right());
+        let (x_first, x_last) =
+            repeat_range(start.x, width, view_start.x, view_end.x, data.repeat.x);
+        let (y_first, y_last) =
+            repeat_range(start.y, height, view_start.y, view_end.y, data.repeat.y);
+        let mut copies = vec![];
+        for y in y_first..=y_last {
+            for x in x_first..=x_last {
+                copies.push(Vector2d {
+                    x: offset.x + x as f32 * width,
+                    y: offset.y + y as f32 * height,
+                });
+            }
+        }
+        copies
+    }
+
+    /// every sprite the view sees with how far it has to be moved to get drawn, one entry per
+    /// copy for repeating sprites
+    pub(crate) fn visible_sprites<'a>(
+        &self,
+        query: SpriteQuery<'a>,
+        stats: &mut FrameStats,
+    ) -> Vec<(&'a SpriteData, Vector2d)> {
+        let SpriteQuery {
+            part_map,
+            sprite_keys,
+            max_sprite_extent,
+            visibility,
+            view,
+            filter,
+        } = query;
+        let bound = view.get_bounding_rect();
+        let mut visible = vec![];
+        // each sprite is only ever looked up once, whatever isn't found or gets left out is culled
+        let mut shown = 0;
+
+        for (layer, offset, lookup) in self.lookups(&bound, max_sprite_extent) {
+            let regions = part_map
+                .points_to_regions(lookup.top_left(), lookup.bottom_right())
+                .unwrap();
+            for reg in regions {
+                stats.regions += 1;
+                let points = match part_map.raw_map.get(&reg) {
+                    Some(points) => points,
+                    None => continue,
+                };
+                for point_data in points.iter().flatten() {
+                    let data: &SpriteData = match point_data {
+                        SpriteDataPoint::TopLeft(data) => data,
+                        SpriteDataPoint::BottomRight => continue,
+                    };
+                    if self.is_looked_up(layer, data)
+                        && !data.repeat.any()
+                        && is_shown(data, offset, &bound, filter, visibility)
+                    {
+                        visible.push((data, offset));
+                        shown += 1;
+                    }
+                }
+            }
+        }
+
+        // repeating sprites cover everything, they're tiled over the view instead of looked up
+        for id in self.repeating.iter() {
+            let data: &SpriteData = match sprite_keys.get(id).map(|(key, _)| &part_map[key]) {
+                Some(SpriteDataPoint::TopLeft(data))
+                    if filter.accepts(data) && visibility.shows(data) =>
+                {
+                    data
+                }
+                _ => continue,
+            };
+            let copies = self.repeat_copies(data, &bound);
+            if !copies.is_empty() {
+                shown += 1;
+            }
+            for copy in copies {
+                visible.push((data, copy));
+            }
+        }
+        stats.culled += (sprite_keys.len() as u32).saturating_sub(shown);
+        visible
+    }
+}
+
+// found by a lookup and drawn, `offset` being where the lookup moves it to
+pub(crate) fn is_shown(
+    data: &SpriteData,
+    offset: Vector2d,
+    bound: &Rect,
+    filter: PassFilter,
+    visibility: &Visibility,
+) -> bool {
+    let mut rect = data.rect.get_bounding_rect();
+    rect.offset_pos(offset);
+    rect.overlaps(bound) && filter.accepts(data) && visibility.shows(data)
+}
+
+impl Scene {
+    /// how fast a layer moves with the camera, 1 is the world, 0 stays put on the screen and
+    /// anything in between is a background further away, sprites on the layer get moved by the
+    /// top left of the view times 1 - factor
+    pub(crate) fn set_layer_parallax(&mut self, layer: u8, is_ui: bool, x: f32, y: f32) {
+        let depth = if is_ui {
+            layer as u16
+        } else {
+            layer as u16 + 128
+        };
+        let factors = &mut self.parallax.factors;
+        if x == 1.0 && y == 1.0 {
+            factors.remove(&depth);
+        } else {
+            factors.insert(depth, Vector2d { x, y });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeat_range_covers_the_view() {
+        // copies 10 long starting at 5, the view from 0 to 30 sees copies -1 through 2
+        assert_eq!(repeat_range(5.0, 10.0, 0.0, 30.0, true), (-1, 2));
+        // lined up exactly, no extra copy past the end
+        assert_eq!(repeat_range(0.0, 10.0, 0.0, 30.0, true), (0, 2));
+        assert_eq!(repeat_range(5.0, 10.0, 0.0, 30.0, false), (0, 0));
+        assert_eq!(repeat_range(5.0, 0.0, 0.0, 30.0, true), (0, 0));
+        // capped for tiny copies
+        let (first, last) = repeat_range(0.0, 0.001, 0.0, 1000.0, true);
+        assert_eq!(last - first, MAX_REPEAT);
+    }
+
+    #[test]
+    fn world_layers_past_127_keep_their_own_factor() {
+        let mut parallax = Parallax::default();
+        parallax
+            .factors
+            .insert(200 + 128, Vector2d { x: 0.5, y: 0.5 });
+        let view = Rect::new_raw(100.0, 100.0, 0.0, 10.0, 10.0);
+        let offset = parallax.offset(200.0 + 128.0, &view);
+        assert_eq!((offset.x, offset.y), (50.0, 50.0));
+        let offset = parallax.offset(255.0, &view);
+        assert_eq!((offset.x, offset.y), (0.0, 0.0));
+    }
+
+    #[test]
+    fn sprites_outside_the_view_arent_shown() {
+        let rect = Rect::new_raw(50.0, 0.0, 128.0, 10.0, 10.0);
+        let frame = Frame::from_uv(0.0, 0.0, 1.0, 1.0);
+        let data = SpriteData::new(SpriteId(0), rect, frame, &rect);
+        let view = Rect::new_raw(0.0, 0.0, 0.0, 40.0, 40.0);
+        let filter = PassFilter::Screen(LayerMask::all());
+        let visibility = Visibility::new();
+        let no_offset = Vector2d { x: 0.0, y: 0.0 };
+        assert!(!is_shown(&data, no_offset, &view, filter, &visibility));
+        // a parallax layer can bring it in
+        let offset = Vector2d { x: -20.0, y: 0.0 };
+        assert!(is_shown(&data, offset, &view, filter, &visibility));
+    }
+
+    #[test]
+    fn parallax_layers_get_a_lookup_of_their_own() {
+        let mut parallax = Parallax::default();
+        parallax.factors.insert(130, Vector2d { x: 0.5, y: 0.5 });
+        let view = Rect::new_raw(100.0, 100.0, 0.0, 10.0, 10.0);
+        let lookups = parallax.lookups(&view, Vector2d { x: 4.0, y: 4.0 });
+        assert_eq!(lookups.len(), 2);
+        let (layer, _, world) = lookups[0];
+        assert_eq!(layer, None);
+        assert_eq!(world.top_left().x, 96.0);
+        // the layer moved along by 50, so the view lands 50 back on it
+        let (layer, offset, back) = lookups[1];
+        assert_eq!((layer, offset.x), (Some(130), 50.0));
+        assert_eq!((back.top_left().x, back.bottom_right().x), (46.0, 60.0));
+    }
+}
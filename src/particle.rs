@@ -0,0 +1,444 @@
+use crate::*;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct EmitterId(u32);
+
+pub(crate) trait Lerp: Copy {
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        Color::lerp(self, other, t)
+    }
+}
+
+/// a value over the life of a particle, keys are (life from 0 to 1, value) and are linearly
+/// interpolated, before the first key and after the last one the value is held
+#[derive(Debug, Clone)]
+pub(crate) struct Curve<T: Lerp> {
+    keys: ArrayVec<(f32, T), 8>,
+}
+
+impl<T: Lerp> Curve<T> {
+    pub(crate) fn constant(value: T) -> Self {
+        let mut keys = ArrayVec::new();
+        keys.push((0.0, value));
+        Self { keys }
+    }
+
+    pub(crate) fn linear(from: T, to: T) -> Self {
+        let mut keys = ArrayVec::new();
+        keys.push((0.0, from));
+        keys.push((1.0, to));
+        Self { keys }
+    }
+
+    // keys have to be added in order of life
+    pub(crate) fn with_key(mut self, life: f32, value: T) -> Result<Self, &'static str> {
+        if self.keys.last().is_some_and(|(last, _)| *last > life) {
+            return Err("curve keys out of order");
+        }
+        self.keys
+            .try_push((life, value))
+            .map_err(|_| "too many curve keys")?;
+        Ok(self)
+    }
+
+    fn sample(&self, life: f32) -> T {
+        let next = self.keys.iter().position(|(key, _)| *key > life);
+        match next {
+            Some(0) => self.keys[0].1,
+            None => self.keys[self.keys.len() - 1].1,
+            Some(index) => {
+                let (start, from) = self.keys[index - 1];
+                let (end, to) = self.keys[index];
+                from.lerp(to, (life - start) / (end - start))
+            }
+        }
+    }
+}
+
+/// how the particles of an emitter behave, ranges are (min, max) and get picked from at random
+/// for every particle
+#[derive(Clone)]
+pub(crate) struct EmitterConfig {
+    // particles per second, 0 to only emit bursts
+    pub(crate) rate: f32,
+    // size of the pool, nothing is spawned while it's full. fixed once the emitter is made
+    pub(crate) max_particles: usize,
+    // in seconds
+    pub(crate) lifetime: (f32, f32),
+    pub(crate) speed: (f32, f32),
+    // in rad, the particles go out within spread / 2 of either side of it
+    pub(crate) direction: f32,
+    pub(crate) spread: f32,
+    // along the direction each particle travels in, negative slows them down
+    pub(crate) acceleration: f32,
+    // same for every particle, in world units per second squared
+    pub(crate) gravity: Vector2d,
+    // in rad per second
+    pub(crate) angular_velocity: (f32, f32),
+    // multiplier on the size of the frame
+    pub(crate) size: Curve<f32>,
+    pub(crate) color: Curve<Color>,
+    pub(crate) opacity: Curve<f32>,
+}
+
+impl Default for EmitterConfig {
+    fn default() -> Self {
+        Self {
+            rate: 10.0,
+            max_particles: 64,
+            lifetime: (1.0, 1.0),
+            speed: (50.0, 50.0),
+            direction: 0.0,
+            spread: std::f32::consts::TAU,
+            acceleration: 0.0,
+            gravity: Vector2d { x: 0.0, y: 0.0 },
+            angular_velocity: (0.0, 0.0),
+            size: Curve::constant(1.0),
+            color: Curve::constant(Color::white()),
+            opacity: Curve::constant(1.0),
+        }
+    }
+}
+
+pub(crate) enum ParticleFrame {
+    Static(Frame),
+    // one playback shared by every particle of the emitter
+    Animated(Playback),
+}
+
+#[derive(Clone, Copy)]
+struct Particle {
+    pos: Vector2d,
+    velocity: Vector2d,
+    rotation: f32,
+    angular_velocity: f32,
+    age: f32,
+    lifetime: f32,
+}
+
+/// spawns and moves its particles in Scene::update, none of it allocates once the pool is made
+pub(crate) struct Emitter {
+    config: EmitterConfig,
+    // where new particles spawn, the ones already out don't follow it
+    pub(crate) pos: Vector2d,
+    // stops spawning but lets the live particles run out
+    pub(crate) is_emitting: bool,
+    depth: f32,
+    frame: ParticleFrame,
+    // size of the frame at scale 1
+    frame_size: Vector2d,
+    particles: Vec<Particle>,
+    // fraction of a particle left over from the last update
+    spawn_debt: f32,
+    pending_burst: usize,
+    rng: u32,
+    // covers every live particle as of the last update, for culling
+    bounds: Rect,
+}
+
+impl Emitter {
+    fn new(
+        pos: Vector2d,
+        config: EmitterConfig,
+        frame: ParticleFrame,
+        frame_size: Vector2d,
+        depth: f32,
+        seed: u32,
+    ) -> Self {
+        Self {
+            particles: Vec::with_capacity(config.max_particles),
+            config,
+            pos,
+            is_emitting: true,
+            depth,
+            frame,
+            frame_size,
+            spawn_debt: 0.0,
+            pending_burst: 0,
+            // xorshift can't start at 0
+            rng: seed.wrapping_mul(0x9e37_79b9) | 1,
+            bounds: Rect::new_raw(pos.x, pos.y, depth, 0.0, 0.0),
+        }
+    }
+
+    // xorshift32, good enough for where sparks go
+    fn random(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1 << 24) as f32
+    }
+
+    fn random_in(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.random()
+    }
+
+    // spawns on the next update regardless of the rate
+    pub(crate) fn burst(&mut self, count: usize) {
+        self.pending_burst = self.pending_burst.saturating_add(count);
+    }
+
+    pub(crate) fn config(&self) -> &EmitterConfig {
+        &self.config
+    }
+
+    // the live particles keep going, the pool can't change size since that would allocate
+    pub(crate) fn set_config(&mut self, config: EmitterConfig) -> Result<(), &'static str> {
+        if config.max_particles != self.config.max_particles {
+            return Err("max_particles can't change once the emitter is made");
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    pub(crate) fn particle_count(&self) -> usize {
+        self.particles.len()
+    }
+
+    fn spawn(&mut self) {
+        if self.particles.len() >= self.config.max_particles {
+            return;
+        }
+        let angle = self.config.direction + (self.random() - 0.5) * self.config.spread;
+        let speed = self.random_in(self.config.speed);
+        let mut velocity = Vector2d { x: speed, y: 0.0 };
+        velocity.set_rotation(angle);
+        let particle = Particle {
+            pos: self.pos,
+            velocity,
+            rotation: 0.0,
+            angular_velocity: self.random_in(self.config.angular_velocity),
+            age: 0.0,
+            lifetime: self.random_in(self.config.lifetime).max(f32::EPSILON),
+        };
+        self.particles.push(particle);
+    }
+
+    fn update(&mut self, delta_t: f32) {
+        let mut index = 0;
+        while index < self.particles.len() {
+            let particle = &mut self.particles[index];
+            particle.age += delta_t;
+            if particle.age >= particle.lifetime {
+                // order doesn't matter, they all go in the same batch
+                self.particles.swap_remove(index);
+                continue;
+            }
+            let speed = (particle.velocity.x * particle.velocity.x
+                + particle.velocity.y * particle.velocity.y)
+                .sqrt();
+            if speed > 0.0 {
+                // never turns a particle around, it just stops
+                let new_speed = (speed + self.config.acceleration * delta_t).max(0.0);
+                particle.velocity = particle.velocity * (new_speed / speed);
+            }
+            particle.velocity += self.config.gravity * delta_t;
+            particle.pos += particle.velocity * delta_t;
+            particle.rotation += particle.angular_velocity * delta_t;
+            index += 1;
+        }
+
+        if self.is_emitting {
+            self.spawn_debt += self.config.rate * delta_t;
+        }
+        // whatever doesn't fit in the pool is dropped, not spawned later
+        let free = self.config.max_particles - self.particles.len();
+        let count = (self.spawn_debt as usize)
+            .saturating_add(self.pending_burst)
+            .min(free);
+        self.spawn_debt = self.spawn_debt.fract();
+        self.pending_burst = 0;
+        for _ in 0..count {
+            self.spawn();
+        }
+
+        // the largest a particle can get, turned any which way
+        let max_size = self
+            .config
+            .size
+            .keys
+            .iter()
+            .fold(0.0f32, |max, (_, size)| max.max(*size));
+        let reach = (self.frame_size.x.hypot(self.frame_size.y)) * max_size * 0.5;
+        let (mut left, mut top) = (f32::MAX, f32::MAX);
+        let (mut right, mut bottom) = (f32::MIN, f32::MIN);
+        for particle in &self.particles {
+            left = left.min(particle.pos.x - reach);
+            top = top.min(particle.pos.y - reach);
+            right = right.max(particle.pos.x + reach);
+            bottom = bottom.max(particle.pos.y + reach);
+        }
+        self.bounds = if self.particles.is_empty() {
+            Rect::new_raw(self.pos.x, self.pos.y, self.depth, 0.0, 0.0)
+        } else {
+            Rect::new_raw(left, top, self.depth, right - left, bottom - top)
+        };
+    }
+
+    pub(crate) fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    /// loads every live particle if the emitter can be seen from `view`
+    pub(crate) fn load(&self, render_data: &mut RenderData, sequences: &Sequences, view: &Rect) {
+        if self.particles.is_empty() || !self.bounds.overlaps(view) {
+            return;
+        }
+        let frame = match &self.frame {
+            ParticleFrame::Static(frame) => *frame,
+            ParticleFrame::Animated(playback) => sequences.frame(playback).0,
+        };
+        for particle in &self.particles {
+            let life = particle.age / particle.lifetime;
+            let size = self.frame_size * self.config.size.sample(life);
+            let mut color = self.config.color.sample(life);
+            color.a *= self.config.opacity.sample(life);
+            let mut rect = Rect::new_raw(
+                particle.pos.x - size.x * 0.5,
+                particle.pos.y - size.y * 0.5,
+                self.depth,
+                size.x,
+                size.y,
+            );
+            if particle.rotation != 0.0 {
+                rect.set_rotation_with_origin(particle.rotation, rect.center_origin());
+            }
+            render_data.load_raw(rect, frame, color);
+        }
+    }
+}
+
+impl Scene {
+    pub(crate) fn add_emitter(
+        &mut self,
+        pos: Vector2d,
+        config: EmitterConfig,
+        tex: &str,
+        layer: u8,
+        is_ui: bool,
+    ) -> Result<EmitterId, &'static str> {
+        let (frame, frame_rect) = self.atlas_frame(tex)?;
+        let size = Vector2d {
+            x: frame_rect.width(),
+            y: frame_rect.height(),
+        };
+        Ok(self.insert_emitter(
+            pos,
+            config,
+            ParticleFrame::Static(frame),
+            size,
+            layer,
+            is_ui,
+        ))
+    }
+
+    /// same as add_emitter but the frames come from a sequence added with Scene::add_sequence,
+    /// `size` is the size of a particle at scale 1
+    pub(crate) fn add_animated_emitter(
+        &mut self,
+        pos: Vector2d,
+        config: EmitterConfig,
+        seq: &str,
+        size: Vector2d,
+        layer: u8,
+        is_ui: bool,
+    ) -> Result<EmitterId, &'static str> {
+        let playback = self.sequences.start(seq, None)?;
+        Ok(self.insert_emitter(
+            pos,
+            config,
+            ParticleFrame::Animated(playback),
+            size,
+            layer,
+            is_ui,
+        ))
+    }
+
+    fn insert_emitter(
+        &mut self,
+        pos: Vector2d,
+        config: EmitterConfig,
+        frame: ParticleFrame,
+        frame_size: Vector2d,
+        layer: u8,
+        is_ui: bool,
+    ) -> EmitterId {
+        let depth = if is_ui {
+            layer as f32
+        } else {
+            layer as f32 + 128.0
+        };
+        let id = EmitterId(self.next_emitter_id);
+        self.next_emitter_id += 1;
+        let emitter = Emitter::new(pos, config, frame, frame_size, depth, id.0 + 1);
+        self.emitters.insert(id, emitter);
+        id
+    }
+
+    pub(crate) fn remove_emitter(&mut self, id: EmitterId) -> Result<(), &'static str> {
+        self.emitters.remove(&id).ok_or("invalid emitter id")?;
+        Ok(())
+    }
+
+    pub(crate) fn emitter_mut(&mut self, id: EmitterId) -> Result<&mut Emitter, &'static str> {
+        self.emitters.get_mut(&id).ok_or("invalid emitter id")
+    }
+
+    pub(crate) fn update_particles(&mut self, delta_t: f32) {
+        for emitter in self.emitters.values_mut() {
+            if let ParticleFrame::Animated(playback) = &mut emitter.frame {
+                self.sequences.step(playback, delta_t);
+            }
+            emitter.update(delta_t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emitter(max_particles: usize) -> Emitter {
+        let config = EmitterConfig {
+            rate: 0.0,
+            max_particles,
+            ..EmitterConfig::default()
+        };
+        let frame = ParticleFrame::Static(Frame::from_uv(0.0, 0.0, 1.0, 1.0));
+        let size = Vector2d { x: 4.0, y: 4.0 };
+        Emitter::new(Vector2d { x: 0.0, y: 0.0 }, config, frame, size, 128.0, 1)
+    }
+
+    #[test]
+    fn burst_fills_the_pool_without_growing_it() {
+        let mut emitter = emitter(8);
+        emitter.burst(usize::MAX);
+        emitter.burst(20);
+        emitter.update(0.1);
+        assert_eq!(emitter.particle_count(), 8);
+        assert_eq!(emitter.particles.capacity(), 8);
+        // the rest isn't held over for later
+        emitter.update(0.1);
+        assert_eq!(emitter.particle_count(), 8);
+    }
+
+    #[test]
+    fn pool_size_is_fixed() {
+        let mut emitter = emitter(8);
+        let mut config = emitter.config().clone();
+        config.rate = 30.0;
+        assert!(emitter.set_config(config.clone()).is_ok());
+        config.max_particles = 16;
+        assert!(emitter.set_config(config).is_err());
+    }
+}
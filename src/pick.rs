@@ -0,0 +1,364 @@
+use crate::*;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct HitMaskId(u32);
+
+// one bit per pixel of a frame, set where it's solid enough to be clicked
+pub(crate) struct HitMask {
+    width: u32,
+    height: u32,
+    bits: Vec<u64>,
+}
+
+impl HitMask {
+    // s and t go from 0 to 1 across the frame
+    fn contains(&self, s: f32, t: f32) -> bool {
+        let x = ((s * self.width as f32) as u32).min(self.width - 1);
+        let y = ((t * self.height as f32) as u32).min(self.height - 1);
+        let index = (y * self.width + x) as usize;
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+}
+
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PointerEventKind {
+    Down = 0,
+    Up = 1,
+    Enter = 2,
+    Leave = 3,
+    // the pointer moved while down, goes to the sprite it went down on even once it's left it
+    Drag = 4,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PointerEvent {
+    pub(crate) kind: PointerEventKind,
+    pub(crate) sprite: SpriteId,
+    // where the pointer is in world coords, or in screen pixels for ui sprites
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+}
+
+#[derive(Default)]
+pub(crate) struct PointerState {
+    pub(crate) hovered: Option<SpriteId>,
+    pub(crate) pressed: Option<SpriteId>,
+    // what the last pointer call produced
+    pub(crate) events: Vec<PointerEvent>,
+}
+
+// lower layers are drawn on top, and sprites added later within a layer
+fn is_drawn_over(data: &SpriteData, top: Option<&SpriteData>) -> bool {
+    match top {
+        None => true,
+        Some(top) => {
+            let (depth, top_depth) = (data.rect.top_left.2, top.rect.top_left.2);
+            depth < top_depth || (depth == top_depth && data.id > top.id)
+        }
+    }
+}
+
+impl Scene {
+    /// screen coords are in pixels from the top left of a screen of the size set with
+    /// set_screen_size, mapped onto what the camera of the last update saw
+    pub(crate) fn screen_to_world(&self, screen_x: f32, screen_y: f32) -> Vector2d {
+        let screen = self.screen_extent();
+        self.last_camera
+            .rect
+            .point_at(screen_x / screen.x, screen_y / screen.y)
+    }
+
+    pub(crate) fn screen_extent(&self) -> Vector2d {
+        self.screen_size.unwrap_or_else(|| {
+            let (width, height) = self.last_camera.rect.edge_lengths();
+            Vector2d {
+                x: width,
+                y: height,
+            }
+        })
+    }
+
+    pub(crate) fn set_screen_size(&mut self, width: f32, height: f32) {
+        self.screen_size = Some(Vector2d {
+            x: width,
+            y: height,
+        });
+    }
+
+    /// the sprite drawn on top at the screen position, lower layers go on top and ui goes on top
+    /// of the world, sprites added later go on top within a layer
+    pub(crate) fn pick(&self, screen_x: f32, screen_y: f32) -> Option<SpriteId> {
+        let screen = Vector2d {
+            x: screen_x,
+            y: screen_y,
+        };
+        self.pick_screen(screen, |_| true)
+    }
+
+    // every view drawn under a screen position, the one drawn last first, with where the
+    // position lands in it and which sprites it draws. the ui goes over every camera and
+    // viewports over the main one
+    fn views_at(&self, screen: Vector2d) -> Vec<(Rect, Vector2d, PassFilter)> {
+        let mut views = vec![(self.ui_view(), self.ui_origin() + screen, PassFilter::Ui)];
+        let extent = self.screen_extent();
+        let (s, t) = (screen.x / extent.x, screen.y / extent.y);
+        let mut ids: Vec<ViewportId> = self.viewports.keys().copied().collect();
+        ids.sort();
+        for id in ids.iter().rev() {
+            let viewport = &self.viewports[id];
+            let [x, y, width, height] = viewport.rect;
+            let (s, t) = ((s - x) / width, (t - y) / height);
+            if (0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t) {
+                let view = viewport.camera.rect;
+                let pass = PassFilter::Screen(viewport.layers);
+                views.push((view, view.point_at(s, t), pass));
+            }
+        }
+        let cam = self.last_camera.rect;
+        let point = self.screen_to_world(screen.x, screen.y);
+        views.push((cam, point, PassFilter::Screen(self.screen_layers)));
+        views
+    }
+
+    // a viewport that has nothing under the pointer lets the views below it through
+    fn pick_screen(
+        &self,
+        screen: Vector2d,
+        filter: impl Fn(&SpriteData) -> bool,
+    ) -> Option<SpriteId> {
+        self.views_at(screen)
+            .into_iter()
+            .find_map(|(view, point, pass)| self.pick_in(&view, point, pass, &filter))
+            .map(|data| data.id)
+    }
+
+    // the same lookups visible_sprites makes, a sprite is hit where it's drawn so the point is
+    // moved back by the offset of its parallax layer or repeated copy
+    fn pick_in(
+        &self,
+        view: &Rect,
+        point: Vector2d,
+        pass: PassFilter,
+        filter: &impl Fn(&SpriteData) -> bool,
+    ) -> Option<&SpriteData> {
+        let bound = view.get_bounding_rect();
+        let is_candidate = |data: &SpriteData| {
+            pass.accepts(data)
+                && self.visibility.shows(data)
+                && filter(data)
+                && self.clip_contains(data, point)
+        };
+        let mut top: Option<&SpriteData> = None;
+        for (layer, offset, _) in self.parallax.lookups(&bound, self.max_sprite_extent) {
+            let local = point - offset;
+            // the data lives at the top left of the bounding rect, which is at most this far away
+            let regions = match self
+                .part_map
+                .points_to_regions(local - self.max_sprite_extent, local)
+            {
+                Ok(regions) => regions,
+                Err(_) => continue,
+            };
+            for reg in regions {
+                let points = match self.part_map.raw_map.get(&reg) {
+                    Some(points) => points,
+                    None => continue,
+                };
+                for point_data in points.iter().flatten() {
+                    let data = match point_data {
+                        SpriteDataPoint::TopLeft(data) => data,
+                        SpriteDataPoint::BottomRight => continue,
+                    };
+                    if !self.parallax.is_looked_up(layer, data)
+                        || data.repeat.any()
+                        || !is_candidate(data)
+                        || !self.hits(data, local)
+                    {
+                        continue;
+                    }
+                    if is_drawn_over(data, top) {
+                        top = Some(data);
+                    }
+                }
+            }
+        }
+        for id in self.parallax.repeating.iter() {
+            let data = match self.sprite_data(*id) {
+                Ok(data) if is_candidate(data) => data,
+                _ => continue,
+            };
+            let is_hit = self
+                .parallax
+                .repeat_copies(data, &bound)
+                .into_iter()
+                .any(|copy| self.hits(data, point - copy));
+            if is_hit && is_drawn_over(data, top) {
+                top = Some(data);
+            }
+        }
+        top
+    }
+
+    // nothing of a clipped sprite is drawn outside its clip, which is where the sprite is drawn
+    fn clip_contains(&self, data: &SpriteData, point: Vector2d) -> bool {
+        let clip = match data.clip.and_then(|id| self.clips.get(&id)) {
+            Some(clip) => clip,
+            None => return true,
+        };
+        match clip.rect.local_coords(point) {
+            Some((s, t)) => (0.0..=1.0).contains(&s) && (0.0..=1.0).contains(&t),
+            None => false,
+        }
+    }
+
+    fn hits(&self, data: &SpriteData, point: Vector2d) -> bool {
+        let (s, t) = match data.rect.local_coords(point) {
+            Some(coords) => coords,
+            None => return false,
+        };
+        if !(0.0..=1.0).contains(&s) || !(0.0..=1.0).contains(&t) {
+            return false;
+        }
+        // the mask is of the art, which a flip mirrors across the rect
+        let s = if data.flip_x { 1.0 - s } else { s };
+        let t = if data.flip_y { 1.0 - t } else { t };
+        match data.hit_mask.and_then(|id| self.hit_masks.get(&id)) {
+            Some(mask) => mask.contains(s, t),
+            None => true,
+        }
+    }
+
+    /// `alpha` is one byte per pixel of the frame the mask is for, row by row, anything above
+    /// `threshold` can be clicked
+    pub(crate) fn add_hit_mask(
+        &mut self,
+        width: u32,
+        height: u32,
+        alpha: &[u8],
+        threshold: u8,
+    ) -> Result<HitMaskId, &'static str> {
+        if width == 0 || height == 0 || alpha.len() != (width * height) as usize {
+            return Err("hit mask size doesn't match its alpha");
+        }
+        let mut bits = vec![0u64; alpha.len().div_ceil(64)];
+        for (index, value) in alpha.iter().enumerate() {
+            if *value > threshold {
+                bits[index / 64] |= 1 << (index % 64);
+            }
+        }
+        let id = HitMaskId(self.next_hit_mask_id);
+        self.next_hit_mask_id += 1;
+        self.hit_masks.insert(
+            id,
+            HitMask {
+                width,
+                height,
+                bits,
+            },
+        );
+        Ok(id)
+    }
+
+    // sprites using it go back to being hit anywhere on their rect
+    pub(crate) fn remove_hit_mask(&mut self, id: HitMaskId) -> Result<(), &'static str> {
+        self.hit_masks.remove(&id).ok_or("invalid hit mask id")?;
+        Ok(())
+    }
+
+    // only interactive sprites get events, the rest are let through
+    fn update_hover(&mut self, screen: Vector2d) -> Option<SpriteId> {
+        let hit = self.pick_screen(screen, |data| data.is_interactive);
+        if hit != self.pointer.hovered {
+            if let Some(sprite) = self.pointer.hovered {
+                self.push_pointer_event(PointerEventKind::Leave, sprite, screen);
+            }
+            if let Some(sprite) = hit {
+                self.push_pointer_event(PointerEventKind::Enter, sprite, screen);
+            }
+            self.pointer.hovered = hit;
+        }
+        hit
+    }
+
+    fn push_pointer_event(&mut self, kind: PointerEventKind, sprite: SpriteId, screen: Vector2d) {
+        let point = match self.sprite_data(sprite) {
+            Ok(data) if data.is_screen_space => screen,
+            // through the view that draws the sprite under the pointer, the main camera when
+            // none of them does
+            Ok(data) => self
+                .views_at(screen)
+                .into_iter()
+                .find(|(_, _, pass)| pass.accepts(data))
+                .map(|(_, point, _)| point)
+                .unwrap_or_else(|| self.screen_to_world(screen.x, screen.y)),
+            Err(_) => self.screen_to_world(screen.x, screen.y),
+        };
+        self.pointer.events.push(PointerEvent {
+            kind,
+            sprite,
+            x: point.x,
+            y: point.y,
+        });
+    }
+
+    pub(crate) fn pointer_move(&mut self, screen_x: f32, screen_y: f32) -> WasmPointerEventPtr {
+        self.pointer.events.clear();
+        let screen = Vector2d {
+            x: screen_x,
+            y: screen_y,
+        };
+        self.update_hover(screen);
+        if let Some(sprite) = self.pointer.pressed {
+            self.push_pointer_event(PointerEventKind::Drag, sprite, screen);
+        }
+        self.handle_ui_pointer();
+        WasmPointerEventPtr::new(&self.pointer.events)
+    }
+
+    pub(crate) fn pointer_down(&mut self, screen_x: f32, screen_y: f32) -> WasmPointerEventPtr {
+        self.pointer.events.clear();
+        let screen = Vector2d {
+            x: screen_x,
+            y: screen_y,
+        };
+        let hit = self.update_hover(screen);
+        if let Some(sprite) = hit {
+            self.push_pointer_event(PointerEventKind::Down, sprite, screen);
+        }
+        self.pointer.pressed = hit;
+        self.handle_ui_pointer();
+        WasmPointerEventPtr::new(&self.pointer.events)
+    }
+
+    // the up goes to the sprite the pointer went down on, or the one under it if there's none
+    pub(crate) fn pointer_up(&mut self, screen_x: f32, screen_y: f32) -> WasmPointerEventPtr {
+        self.pointer.events.clear();
+        let screen = Vector2d {
+            x: screen_x,
+            y: screen_y,
+        };
+        let hit = self.update_hover(screen);
+        if let Some(sprite) = self.pointer.pressed.take().or(hit) {
+            self.push_pointer_event(PointerEventKind::Up, sprite, screen);
+        }
+        self.handle_ui_pointer();
+        WasmPointerEventPtr::new(&self.pointer.events)
+    }
+
+    /// what the last pointer_move/down/up produced
+    pub(crate) fn pointer_events(&self) -> &[PointerEvent] {
+        &self.pointer.events
+    }
+
+    // a removed sprite just stops being hovered or pressed, without a leave
+    pub(crate) fn forget_pointer_target(&mut self, id: SpriteId) {
+        if self.pointer.hovered == Some(id) {
+            self.pointer.hovered = None;
+        }
+        if self.pointer.pressed == Some(id) {
+            self.pointer.pressed = None;
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::*;
+
+/// pixel art settings, see Scene::set_pixel_snap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct PixelSnap {
+    // pixels of art per world unit
+    pub(crate) pixels_per_unit: f32,
+    // the main camera gets rounded to a whole number of screen pixels per art pixel, needs
+    // Scene::set_screen_size
+    pub(crate) integer_zoom: bool,
+    // how many texels every sprite's uv gets pulled in by on each side, half a texel stops the
+    // neighbouring atlas entries from bleeding in
+    pub(crate) uv_inset: f32,
+}
+
+impl PixelSnap {
+    pub(crate) fn new() -> Self {
+        Self {
+            pixels_per_unit: 1.0,
+            integer_zoom: true,
+            uv_inset: 0.0,
+        }
+    }
+}
+
+impl Camera {
+    // set_zoom_level for pixel art, every art pixel covers `level` screen pixels
+    pub(crate) fn set_integer_zoom_level(
+        &mut self,
+        level: u32,
+        screen: Vector2d,
+        pixels_per_unit: f32,
+    ) {
+        let scale = level.max(1) as f32 * pixels_per_unit;
+        let (width, height) = (screen.x / scale, screen.y / scale);
+        let center = self.rect.center_global();
+        *self = Camera::new(
+            center.x - width * 0.5,
+            center.y - height * 0.5,
+            width,
+            height,
+        );
+    }
+
+    /// the closest zoom where every art pixel covers the same whole number of screen pixels, at
+    /// least one, kept centered and turned as far as the shake turned it
+    pub(crate) fn snapped_to_integer_zoom(&self, screen: Vector2d, pixels_per_unit: f32) -> Self {
+        let (width, height) = self.rect.edge_lengths();
+        if width <= 0.0 || height <= 0.0 || pixels_per_unit <= 0.0 {
+            return *self;
+        }
+        let level = (screen.x / (width * pixels_per_unit)).round().max(1.0);
+        // the inverse of Vector2d::set_rotation on the top edge
+        let edge = self.rect.top_right() - self.rect.top_left();
+        let rad = (-edge.y).atan2(edge.x);
+        let center = (self.rect.top_left() + self.rect.bottom_right()) * 0.5;
+        let scale = level * pixels_per_unit;
+        let (width, height) = (screen.x / scale, screen.y / scale);
+        let mut cam = Camera::new(
+            center.x - width * 0.5,
+            center.y - height * 0.5,
+            width,
+            height,
+        );
+        if rad != 0.0 {
+            let origin = cam.rect.center_origin();
+            cam.rect.set_rotation_with_origin(rad, origin);
+        }
+        cam
+    }
+}
+
+impl RenderData {
+    /// moves the quads from `start` on so the top left of each sprite, text and tile chunk lands
+    /// on a pixel of the target, all the quads of one move by the same amount so they don't
+    /// open up seams or change size by a pixel from frame to frame
+    pub(crate) fn snap_quads(&mut self, start: usize, view: &Rect, pixels: Vector2d) {
+        let (width, height) = view.edge_lengths();
+        if pixels.x <= 0.0 || pixels.y <= 0.0 {
+            return;
+        }
+        let pixel = Vector2d {
+            x: width / pixels.x,
+            y: height / pixels.y,
+        };
+        let origin = view.top_left();
+        let mut blocks = self
+            .snap_blocks
+            .iter()
+            .filter(|block| block.start >= start && !block.is_empty())
+            .peekable();
+        let mut index = start;
+        while index < self.sprite_pos.len() {
+            // particles aren't in a block, each of those is snapped on its own
+            let end = match blocks.next_if(|block| block.start == index) {
+                Some(block) => block.end.min(self.sprite_pos.len()),
+                None => index + 1,
+            };
+            let local = self.sprite_pos[index].top_left() - origin;
+            let snapped = Vector2d {
+                x: (local.x / pixel.x).round() * pixel.x,
+                y: (local.y / pixel.y).round() * pixel.y,
+            };
+            for rect in &mut self.sprite_pos[index..end] {
+                rect.offset_pos(snapped - local);
+            }
+            index = end;
+        }
+    }
+
+    // pulls the uv of the quads from `start` on in by uv_inset texels of the sprite's frame
+    pub(crate) fn inset_uvs(&mut self, start: usize, sprite: &SpriteData) {
+        let frame = &sprite.frame;
+        let uv_len =
+            |a: (f32, f32), b: (f32, f32)| ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+        let (frame_u, frame_v) = (
+            uv_len(frame.top_left, frame.top_right),
+            uv_len(frame.top_left, frame.bottom_left),
+        );
+        if sprite.native_size.x <= 0.0 || sprite.native_size.y <= 0.0 {
+            return;
+        }
+        // the inset in uv units
+        let inset_u = self.uv_inset * frame_u / sprite.native_size.x;
+        let inset_v = self.uv_inset * frame_v / sprite.native_size.y;
+        for quad in &mut self.tex_pos[start..] {
+            let (quad_u, quad_v) = (
+                uv_len(quad.top_left, quad.top_right),
+                uv_len(quad.top_left, quad.bottom_left),
+            );
+            // a quad thinner than two insets would turn inside out
+            let s = if quad_u > inset_u * 2.0 {
+                inset_u / quad_u
+            } else {
+                0.0
+            };
+            let t = if quad_v > inset_v * 2.0 {
+                inset_v / quad_v
+            } else {
+                0.0
+            };
+            *quad = quad.sub_frame(s, t, 1.0 - s, 1.0 - t);
+        }
+    }
+}
+
+impl Scene {
+    /// None turns it off, sprites, text, tilemaps and particles all get snapped
+    pub(crate) fn set_pixel_snap(&mut self, snap: Option<PixelSnap>) {
+        self.pixel_snap = snap;
+        self.vert_attrib.uv_inset = snap.map_or(0.0, |snap| snap.uv_inset);
+    }
+
+    /// the screen size and pixels per unit a camera zoom gets rounded to, None without integer
+    /// zoom, see Camera::set_zoom_level
+    pub(crate) fn zoom_grid(&self) -> Option<(Vector2d, f32)> {
+        match (self.pixel_snap, self.screen_size) {
+            (Some(snap), Some(screen)) if snap.integer_zoom => Some((screen, snap.pixels_per_unit)),
+            _ => None,
+        }
+    }
+
+    // the main camera with the integer zoom applied
+    pub(crate) fn snap_camera(&self, cam: Camera) -> Camera {
+        match self.zoom_grid() {
+            Some((screen, pixels_per_unit)) => cam.snapped_to_integer_zoom(screen, pixels_per_unit),
+            None => cam,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_moves_a_block_as_one() {
+        let mut render_data = RenderData::new();
+        render_data
+            .sprite_pos
+            .push(Rect::new_raw(0.3, 0.3, 0.0, 1.0, 1.0));
+        render_data
+            .sprite_pos
+            .push(Rect::new_raw(1.3, 0.3, 0.0, 1.0, 1.0));
+        render_data.snap_blocks.push(0..2);
+        // a particle on its own
+        render_data
+            .sprite_pos
+            .push(Rect::new_raw(5.6, 0.0, 0.0, 1.0, 1.0));
+        let view = Rect::new_raw(0.0, 0.0, 0.0, 10.0, 10.0);
+        render_data.snap_quads(0, &view, Vector2d { x: 10.0, y: 10.0 });
+        let left = |index: usize| render_data.sprite_pos[index].top_left().x;
+        assert!((left(0) - 0.0).abs() < 1e-5);
+        assert!((left(1) - 1.0).abs() < 1e-5);
+        assert!((left(2) - 6.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn integer_zoom_keeps_the_shake_rotation() {
+        let mut cam = Camera::new(0.0, 0.0, 100.0, 50.0);
+        let origin = cam.rect.center_origin();
+        cam.rect.set_rotation_with_origin(0.1, origin);
+        let snapped = cam.snapped_to_integer_zoom(Vector2d { x: 200.0, y: 100.0 }, 1.0);
+        let edge = snapped.rect.top_right() - snapped.rect.top_left();
+        assert!(((-edge.y).atan2(edge.x) - 0.1).abs() < 1e-4);
+        let (width, height) = snapped.rect.edge_lengths();
+        assert!((width - 100.0).abs() < 1e-3 && (height - 50.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn zoom_lands_on_the_grid() {
+        let screen = Vector2d { x: 200.0, y: 100.0 };
+        let mut cam = Camera::new(0.0, 0.0, 100.0, 50.0);
+        // 2.2 screen pixels per art pixel rounds down to 2
+        cam.set_zoom_level(100.0 / 110.0, Some((screen, 1.0)));
+        let (width, height) = cam.rect.edge_lengths();
+        assert!((width - 100.0).abs() < 1e-3 && (height - 50.0).abs() < 1e-3);
+        let mut free = Camera::new(0.0, 0.0, 100.0, 50.0);
+        free.set_zoom_level(100.0 / 110.0, None);
+        assert!((free.rect.edge_lengths().0 - 100.0 / 1.1).abs() < 1e-3);
+    }
+}
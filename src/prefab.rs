@@ -0,0 +1,225 @@
+use crate::*;
+use serde::Deserialize;
+
+fn default_scale() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+/// one sprite of a prefab, placed relative to its parent
+#[derive(Deserialize)]
+pub(crate) struct PrefabNode {
+    // atlas entry
+    frame: String,
+    // from the origin of the parent to the origin of this one, in the parent's rotated and
+    // scaled space
+    #[serde(default)]
+    offset: [f32; 2],
+    // relative to the top left of the sprite like Sprite::set_origin, the top left if missing
+    #[serde(default)]
+    origin: Option<[f32; 2]>,
+    // the parent's layer if missing
+    #[serde(default)]
+    layer: Option<u8>,
+    #[serde(default)]
+    is_ui: bool,
+    // on top of the parent's
+    #[serde(default = "default_scale")]
+    scale: [f32; 2],
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    tint: Option<[f32; 4]>,
+    // sequence started on spawn
+    #[serde(default)]
+    play: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    children: Vec<PrefabNode>,
+}
+
+/// a tree of sprites spawned together with Scene::instantiate, the children move with the root
+/// and get removed along with it
+#[derive(Deserialize)]
+pub(crate) struct Prefab {
+    root: PrefabNode,
+}
+
+impl Prefab {
+    pub(crate) fn from_json(json: &str) -> Result<Self, &'static str> {
+        serde_json::from_str(json).map_err(|_| "invalid prefab json")
+    }
+}
+
+// what a child inherits from the parent it was spawned under
+#[derive(Clone, Copy)]
+struct Placement {
+    pos: Vector2d,
+    rotation: f32,
+    scale: Vector2d,
+    depth: Option<f32>,
+}
+
+impl Scene {
+    /// spawns the whole tree with the origin of the root at `pos`
+    pub(crate) fn instantiate(
+        &mut self,
+        prefab: &Prefab,
+        pos: Vector2d,
+    ) -> Result<Sprite, &'static str> {
+        // every frame and sequence is checked up front so a bad prefab doesn't leave half of
+        // itself behind
+        self.check_prefab_node(&prefab.root)?;
+        let root = Placement {
+            pos,
+            rotation: 0.0,
+            scale: Vector2d { x: 1.0, y: 1.0 },
+            depth: None,
+        };
+        let mut attached = vec![];
+        let spawned = self.spawn_prefab_node(&prefab.root, root, &mut attached);
+        let id = match spawned {
+            Ok(id) => id,
+            // anything that still fails, like a child placed off the map, takes the rest with it
+            Err(err) => {
+                for each in attached {
+                    self.remove_sprite_raw(each).ok();
+                }
+                return Err(err);
+            }
+        };
+        // the root is what the handle is for
+        attached.retain(|each| *each != id);
+        if !attached.is_empty() {
+            self.attached.insert(id, attached);
+        }
+        Ok(Sprite { scene: self, id })
+    }
+
+    fn check_prefab_node(&self, node: &PrefabNode) -> Result<(), &'static str> {
+        self.tex_atlas.get(&node.frame)?;
+        if let Some(seq) = &node.play {
+            self.sequences.start(seq, None)?;
+        }
+        for child in &node.children {
+            self.check_prefab_node(child)?;
+        }
+        Ok(())
+    }
+
+    fn spawn_prefab_node(
+        &mut self,
+        node: &PrefabNode,
+        parent: Placement,
+        spawned: &mut Vec<SpriteId>,
+    ) -> Result<SpriteId, &'static str> {
+        let (sin, cos) = parent.rotation.sin_cos();
+        let offset = Vector2d {
+            x: node.offset[0] * parent.scale.x,
+            y: node.offset[1] * parent.scale.y,
+        };
+        let placement = Placement {
+            pos: parent.pos
+                + Vector2d {
+                    x: offset.x * cos - offset.y * sin,
+                    y: offset.x * sin + offset.y * cos,
+                },
+            rotation: parent.rotation + node.rotation,
+            scale: Vector2d {
+                x: parent.scale.x * node.scale[0],
+                y: parent.scale.y * node.scale[1],
+            },
+            depth: match node.layer {
+                Some(layer) if node.is_ui => Some(layer as f32),
+                Some(layer) => Some(layer as f32 + 128.0),
+                None => parent.depth,
+            },
+        };
+
+        let id = self.add_sprite_raw(Vector2d { x: 0.0, y: 0.0 }, &node.frame)?;
+        spawned.push(id);
+        let data = self.sprite_data_mut(id)?;
+        if let Some([x, y]) = node.origin {
+            data.origin.set(x, y);
+        }
+        data.set_scale(placement.scale.x, placement.scale.y);
+        data.set_rotation(placement.rotation);
+        data.set_pos(placement.pos.x, placement.pos.y);
+        if let Some(depth) = placement.depth {
+            data.rect.set_depth(depth);
+        }
+        if let Some([r, g, b, a]) = node.tint {
+            data.tint = Color::new(r, g, b, a);
+        }
+        self.update_keys(id)?;
+        if let Some(seq) = &node.play {
+            self.play_sprite(id, seq)?;
+        }
+        for tag in &node.tags {
+            self.tag_sprite(id, tag)?;
+        }
+
+        for child in &node.children {
+            self.spawn_prefab_node(child, placement, spawned)?;
+        }
+        Ok(id)
+    }
+
+    // the rest of a prefab follows its root around, a child that would end up off the map stays
+    // where it was
+    pub(crate) fn move_attached(&mut self, id: SpriteId, delta: Vector2d) {
+        self.carry_attached(id, |offset| offset + delta, |_| {});
+    }
+
+    // turned about the origin of the root, by the same amount the root was
+    pub(crate) fn rotate_attached(&mut self, id: SpriteId, rad: f32) {
+        self.carry_attached(
+            id,
+            |mut offset| {
+                offset.set_rotation(rad);
+                offset
+            },
+            |data| data.set_rotation(rad),
+        );
+    }
+
+    // scaled away from the origin of the root, by the same amount the root was
+    pub(crate) fn scale_attached(&mut self, id: SpriteId, x_scale: f32, y_scale: f32) {
+        self.carry_attached(
+            id,
+            |mut offset| {
+                offset.set_scale(x_scale, y_scale);
+                offset
+            },
+            |data| data.set_scale(x_scale, y_scale),
+        );
+    }
+
+    // `place` maps where a child is relative to the origin of the root to where it goes, `change`
+    // does the rest to the child itself
+    fn carry_attached(
+        &mut self,
+        id: SpriteId,
+        place: impl Fn(Vector2d) -> Vector2d,
+        change: impl Fn(&mut SpriteData),
+    ) {
+        let pivot = match self.sprite_data(id) {
+            Ok(data) => data.origin_global(),
+            Err(_) => return,
+        };
+        let attached = match self.attached.remove(&id) {
+            Some(attached) => attached,
+            None => return,
+        };
+        for child in &attached {
+            let carried = self.transform_sprite(*child, |data| {
+                let to = pivot + place(data.origin_global() - pivot);
+                change(data);
+                data.set_pos(to.x, to.y);
+            });
+            // off the map or already removed on its own
+            carried.ok();
+        }
+        self.attached.insert(id, attached);
+    }
+}
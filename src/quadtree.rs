@@ -4,7 +4,7 @@ use crate::*;
 
 // literally the coords
 #[derive(Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub(crate) struct Region(u32, u32);
+pub(crate) struct Region(pub(crate) u32, pub(crate) u32);
 
 pub struct Key(Region, usize);
 
@@ -93,6 +93,8 @@ impl<T> PartitionMap<T> {
     }
 
     pub(crate) fn move_point(&mut self, key: Key, point: Vector2d) -> Result<Key, &'static str> {
+        // checked first, a point that can't go anywhere stays where it was
+        self.point_to_region(point)?;
         let val = self.remove_point(key)?;
         Ok(self.insert_point(point, val)?)
     }
@@ -102,12 +104,13 @@ impl<T> PartitionMap<T> {
         top_left: Vector2d,
         bottom_right: Vector2d,
     ) -> Result<Vec<Region>, &'static str> {
-        let tl = self.point_to_region(top_left)?;
-        let br = self.point_to_region(bottom_right)?;
+        // anything past the edges is clamped to the regions along them
+        let last = (self.size as f32 / self.div_size) as u32 - 1;
+        let to_div = |coord: f32| ((coord / self.div_size).floor().max(0.0) as u32).min(last);
 
         let mut result_vec: Vec<Region> = vec![];
-        for each_y in br.0..tl.0 {
-            for each_x in br.1..tl.1 {
+        for each_y in to_div(top_left.y)..=to_div(bottom_right.y) {
+            for each_x in to_div(top_left.x)..=to_div(bottom_right.x) {
                 result_vec.push(Region(each_x, each_y));
             }
         }
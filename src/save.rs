@@ -0,0 +1,412 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+
+// bumped whenever the layout below changes, older saves get refused instead of misread
+const SAVE_VERSION: u32 = 3;
+
+#[derive(Serialize, Deserialize)]
+struct SceneSave {
+    version: u32,
+    sprites: Vec<SpriteSave>,
+    // every group in the scene by order of id, sprites point into this
+    #[serde(default)]
+    groups: Vec<GroupSave>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct GroupSave {
+    is_hidden: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize)]
+struct SpriteSave {
+    // the atlas entry the frame comes from, so a save survives the atlas getting repacked
+    frame: String,
+    // top left, bottom left, bottom right, top right, the depth holds the layer
+    corners: [[f32; 3]; 4],
+    origin: [f32; 2],
+    scale: [f32; 2],
+    rotation: f32,
+    tint: [f32; 4],
+    sizing: SizingPolicy,
+    #[serde(default)]
+    nine_slice: Option<NineSlice>,
+    // the sequence being played, and the frame index it's on with the seconds spent on it
+    #[serde(default)]
+    playing: Option<String>,
+    #[serde(default)]
+    animation: Option<(usize, f32)>,
+    #[serde(default = "default_true")]
+    is_visible: bool,
+    #[serde(default)]
+    is_interactive: bool,
+    #[serde(default)]
+    repeat: Repeat,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    flip: [bool; 2],
+    // indices into SceneSave::groups
+    #[serde(default)]
+    groups: Vec<u32>,
+    // indices into SceneSave::sprites of the rest of the prefab this is the root of
+    #[serde(default)]
+    attached: Vec<u32>,
+}
+
+impl SpriteSave {
+    fn new(data: &SpriteData, frame: &str, scene: &Scene, groups: &[GroupId]) -> Self {
+        let rect = &data.rect;
+        let corner = |(x, y, z): (f32, f32, f32)| [x, y, z];
+        Self {
+            frame: frame.to_string(),
+            corners: [
+                corner(rect.top_left),
+                corner(rect.bottom_left),
+                corner(rect.bottom_right),
+                corner(rect.top_right),
+            ],
+            origin: [data.origin.vector2d.x, data.origin.vector2d.y],
+            scale: [data.scale.x, data.scale.y],
+            rotation: data.rotation,
+            tint: [data.tint.r, data.tint.g, data.tint.b, data.tint.a],
+            sizing: data.sizing,
+            nine_slice: data.nine_slice,
+            playing: data.playing.clone(),
+            animation: data.playback.map(|playback| playback.position()),
+            is_visible: data.is_visible,
+            is_interactive: data.is_interactive,
+            repeat: data.repeat,
+            flip: [data.flip_x, data.flip_y],
+            tags: data
+                .tags
+                .iter()
+                .map(|tag| scene.tags.name(*tag).to_string())
+                .collect(),
+            groups: data
+                .groups
+                .iter()
+                .filter_map(|group| groups.binary_search(group).ok())
+                .map(|index| index as u32)
+                .collect(),
+            // filled in once every sprite has its index
+            attached: vec![],
+        }
+    }
+
+    // the corners as they'll end up, for checking against the part map before anything is added
+    fn bounding_rect(&self) -> Rect {
+        let [tl, bl, br, tr] = self.corners;
+        Rect {
+            top_left: (tl[0], tl[1], tl[2]),
+            bottom_left: (bl[0], bl[1], bl[2]),
+            bottom_right: (br[0], br[1], br[2]),
+            top_right: (tr[0], tr[1], tr[2]),
+        }
+        .get_bounding_rect()
+    }
+
+    // everything but the frame, the animation and the groups, those go through the atlas, the
+    // seq table and the scene's groups
+    fn apply(&self, data: &mut SpriteData) {
+        let [tl, bl, br, tr] = self.corners;
+        data.rect = Rect {
+            top_left: (tl[0], tl[1], tl[2]),
+            bottom_left: (bl[0], bl[1], bl[2]),
+            bottom_right: (br[0], br[1], br[2]),
+            top_right: (tr[0], tr[1], tr[2]),
+        };
+        data.origin = Origin::new(self.origin[0], self.origin[1]);
+        data.scale = Vector2d {
+            x: self.scale[0],
+            y: self.scale[1],
+        };
+        data.rotation = self.rotation;
+        let [r, g, b, a] = self.tint;
+        data.tint = Color::new(r, g, b, a);
+        data.sizing = self.sizing;
+        data.nine_slice = self.nine_slice;
+        data.is_visible = self.is_visible;
+        data.is_interactive = self.is_interactive;
+        data.repeat = self.repeat;
+        [data.flip_x, data.flip_y] = self.flip;
+    }
+}
+
+// same shape as instantiate makes, every child under one root that isn't attached to anything
+fn check_attached(sprites: &[SpriteSave]) -> Result<(), &'static str> {
+    let mut is_child = vec![false; sprites.len()];
+    for sprite in sprites {
+        for index in &sprite.attached {
+            let seen = is_child
+                .get_mut(*index as usize)
+                .ok_or("sprite attached to one that isn't in the save")?;
+            if *seen {
+                return Err("sprite attached to more than one root");
+            }
+            *seen = true;
+        }
+    }
+    let is_nested = sprites
+        .iter()
+        .zip(&is_child)
+        .any(|(sprite, is_child)| *is_child && !sprite.attached.is_empty());
+    if is_nested {
+        return Err("attached sprite with sprites attached to it");
+    }
+    Ok(())
+}
+
+/// what a load added to the scene
+pub(crate) struct LoadedScene {
+    pub(crate) sprites: Vec<Sprite>,
+    pub(crate) groups: Vec<GroupId>,
+}
+
+impl Scene {
+    /// every sprite showing an atlas frame, in the order they were added. sprites showing a render
+    /// target, the ones the ui owns, text, tilemaps and emitters are left to the code that made
+    /// them
+    fn save(&self) -> SceneSave {
+        let groups = self.group_ids();
+        let mut ids: Vec<SpriteId> = self.sprite_keys.keys().copied().collect();
+        ids.sort();
+        let (saved, mut sprites): (Vec<SpriteId>, Vec<SpriteSave>) = ids
+            .into_iter()
+            .filter(|id| !self.ui.by_sprite.contains_key(id))
+            .filter_map(|id| {
+                let data = self.sprite_data(id).ok()?;
+                let frame = data.frame_name.as_ref()?;
+                Some((id, SpriteSave::new(data, frame, self, &groups)))
+            })
+            .unzip();
+        // children that weren't saved are left out, saved ids are sorted like groups
+        for (id, sprite) in saved.iter().zip(&mut sprites) {
+            if let Some(attached) = self.attached.get(id) {
+                sprite.attached = attached
+                    .iter()
+                    .filter_map(|child| saved.binary_search(child).ok())
+                    .map(|index| index as u32)
+                    .collect();
+            }
+        }
+        SceneSave {
+            version: SAVE_VERSION,
+            sprites,
+            groups: groups
+                .iter()
+                .map(|group| GroupSave {
+                    is_hidden: self.visibility.hidden_groups.contains(group),
+                })
+                .collect(),
+        }
+    }
+
+    pub(crate) fn save_json(&self) -> Result<String, &'static str> {
+        serde_json::to_string(&self.save()).map_err(|_| "failed to write scene json")
+    }
+
+    // same as the json but a lot smaller and faster to read back
+    pub(crate) fn save_binary(&self) -> Result<Vec<u8>, &'static str> {
+        bincode::serialize(&self.save()).map_err(|_| "failed to write scene binary")
+    }
+
+    /// adds the saved sprites and groups on top of whatever is in the scene, both come back in
+    /// the order they were saved in
+    pub(crate) fn load_json(&mut self, json: &str) -> Result<LoadedScene, &'static str> {
+        let save: SceneSave = serde_json::from_str(json).map_err(|_| "invalid scene json")?;
+        self.load(save)
+    }
+
+    pub(crate) fn load_binary(&mut self, bytes: &[u8]) -> Result<LoadedScene, &'static str> {
+        let save: SceneSave = bincode::deserialize(bytes).map_err(|_| "invalid scene binary")?;
+        self.load(save)
+    }
+
+    fn load(&mut self, save: SceneSave) -> Result<LoadedScene, &'static str> {
+        if save.version != SAVE_VERSION {
+            return Err("unsupported scene save version");
+        }
+        // frames, sequences, bounds and groups are checked up front so a bad save doesn't leave
+        // half its sprites behind, anything failing after this drops the handles made so far
+        for sprite in &save.sprites {
+            self.tex_atlas.get(&sprite.frame)?;
+            if let Some(seq) = &sprite.playing {
+                self.sequences.start(seq, sprite.animation)?;
+            }
+            let bound = sprite.bounding_rect();
+            self.part_map.point_to_region(bound.top_left())?;
+            self.part_map.point_to_region(bound.bottom_right())?;
+            if sprite
+                .groups
+                .iter()
+                .any(|group| *group as usize >= save.groups.len())
+            {
+                return Err("sprite in a group that isn't in the save");
+            }
+        }
+        check_attached(&save.sprites)?;
+        let mut sprites = Vec::with_capacity(save.sprites.len());
+        for saved in &save.sprites {
+            let id = self.add_sprite_raw(Vector2d { x: 0.0, y: 0.0 }, &saved.frame)?;
+            let sprite = Sprite { scene: self, id };
+            saved.apply(self.sprite_data_mut(id)?);
+            self.update_keys(id)?;
+            if saved.repeat.any() {
+                self.parallax.repeating.insert(id);
+            }
+            for tag in &saved.tags {
+                self.tag_sprite(id, tag)?;
+            }
+            if let Some(seq) = &saved.playing {
+                self.play_sprite_at(id, seq, saved.animation)?;
+            }
+            sprites.push(sprite);
+        }
+
+        // nothing below can fail
+        let groups: Vec<GroupId> = save
+            .groups
+            .iter()
+            .map(|saved| {
+                let group = self.add_group();
+                if saved.is_hidden {
+                    self.visibility.hidden_groups.insert(group);
+                }
+                group
+            })
+            .collect();
+        for (saved, sprite) in save.sprites.iter().zip(&sprites) {
+            for index in &saved.groups {
+                self.group_add(groups[*index as usize], sprite.id()).ok();
+            }
+            if !saved.attached.is_empty() {
+                let attached = saved
+                    .attached
+                    .iter()
+                    .map(|index| sprites[*index as usize].id())
+                    .collect();
+                self.attached.insert(sprite.id(), attached);
+            }
+        }
+        Ok(LoadedScene { sprites, groups })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> SceneSave {
+        SceneSave {
+            version: SAVE_VERSION,
+            sprites: vec![SpriteSave {
+                frame: "hero".to_string(),
+                corners: [
+                    [10.0, 20.0, 130.0],
+                    [10.0, 52.5, 130.0],
+                    [42.25, 52.5, 130.0],
+                    [42.25, 20.0, 130.0],
+                ],
+                origin: [16.0, 32.0],
+                scale: [-1.0, 0.5],
+                rotation: 0.25,
+                tint: [1.0, 0.5, 0.25, 0.75],
+                sizing: SizingPolicy::KeepAspectRatio,
+                nine_slice: Some(NineSlice::new(1.0, 2.0, 3.0, 4.0).tiled()),
+                playing: Some("hero_run".to_string()),
+                animation: Some((3, 0.0625)),
+                is_visible: false,
+                is_interactive: true,
+                repeat: Repeat { x: true, y: false },
+                tags: vec!["player".to_string()],
+                flip: [true, false],
+                groups: vec![1],
+                attached: vec![],
+            }],
+            groups: vec![
+                GroupSave { is_hidden: false },
+                GroupSave { is_hidden: true },
+            ],
+        }
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let json = serde_json::to_string(&sample()).unwrap();
+        let back: SceneSave = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+        assert_eq!(back.sprites[0].animation, Some((3, 0.0625)));
+        assert!(!back.sprites[0].is_visible);
+        assert!(back.groups[1].is_hidden);
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let bytes = bincode::serialize(&sample()).unwrap();
+        let back: SceneSave = bincode::deserialize(&bytes).unwrap();
+        let json = serde_json::to_string(&sample()).unwrap();
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+    }
+
+    #[test]
+    fn json_fills_in_defaults() {
+        let json = r#"{"version": 3, "sprites": [{"frame": "hero",
+            "corners": [[0, 0, 128], [0, 1, 128], [1, 1, 128], [1, 0, 128]],
+            "origin": [0, 0], "scale": [1, 1], "rotation": 0, "tint": [1, 1, 1, 1],
+            "sizing": "KeepSize"}]}"#;
+        let save: SceneSave = serde_json::from_str(json).unwrap();
+        let sprite = &save.sprites[0];
+        assert!(sprite.is_visible && sprite.animation.is_none() && sprite.groups.is_empty());
+        assert!(save.groups.is_empty() && sprite.attached.is_empty());
+    }
+
+    #[test]
+    fn attached_round_trip() {
+        let mut save = sample();
+        let child = |x: f32| SpriteSave {
+            corners: [
+                [x, 20.0, 130.0],
+                [x, 52.5, 130.0],
+                [x + 32.25, 52.5, 130.0],
+                [x + 32.25, 20.0, 130.0],
+            ],
+            playing: None,
+            animation: None,
+            ..sample().sprites.remove(0)
+        };
+        save.sprites.push(child(50.0));
+        save.sprites.push(child(90.0));
+        save.sprites[0].attached = vec![1, 2];
+        let json = serde_json::to_string(&save).unwrap();
+        let back: SceneSave = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.sprites[0].attached, vec![1, 2]);
+        assert!(check_attached(&back.sprites).is_ok());
+        let bytes = bincode::serialize(&save).unwrap();
+        let back: SceneSave = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(back.sprites[0].attached, vec![1, 2]);
+    }
+
+    #[test]
+    fn refuses_bad_attachments() {
+        let mut save = sample();
+        save.sprites.push(sample().sprites.remove(0));
+        save.sprites[0].attached = vec![2];
+        assert!(check_attached(&save.sprites).is_err());
+        // to itself
+        save.sprites[0].attached = vec![0];
+        save.sprites[1].attached = vec![];
+        assert!(check_attached(&save.sprites).is_err());
+        // under two roots
+        save.sprites[0].attached = vec![1];
+        save.sprites.push(sample().sprites.remove(0));
+        save.sprites[2].attached = vec![1];
+        assert!(check_attached(&save.sprites).is_err());
+        save.sprites[2].attached = vec![];
+        assert!(check_attached(&save.sprites).is_ok());
+    }
+}
@@ -0,0 +1,228 @@
+use crate::*;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SequenceId(u32);
+
+// atlas entries shown one after another, each for frame_time seconds
+struct Sequence {
+    frames: Vec<(Frame, Rect)>,
+    // the atlas entries the frames came from, by the same index
+    names: Vec<String>,
+    frame_time: f32,
+    is_looping: bool,
+}
+
+/// how far into its sequence a sprite, an animated tile or an emitter is, stepped on its own so
+/// it can be paused, saved and picked up again
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Playback {
+    seq: SequenceId,
+    frame: usize,
+    // seconds spent on the current frame
+    elapsed: f32,
+}
+
+impl Playback {
+    /// the frame index and the seconds already spent on it
+    pub(crate) fn position(&self) -> (usize, f32) {
+        (self.frame, self.elapsed)
+    }
+}
+
+// sequence names get interned once, playbacks only hold on to the ids
+#[derive(Default)]
+pub(crate) struct Sequences {
+    ids: HashMap<String, SequenceId>,
+    // indexed by the id
+    sequences: Vec<Sequence>,
+}
+
+impl Sequences {
+    // a name that's already taken gets the new frames, whatever plays it carries on with those
+    fn insert(&mut self, name: &str, sequence: Sequence) -> SequenceId {
+        if let Some(id) = self.ids.get(name) {
+            self.sequences[id.0 as usize] = sequence;
+            return *id;
+        }
+        let id = SequenceId(self.sequences.len() as u32);
+        self.ids.insert(name.to_string(), id);
+        self.sequences.push(sequence);
+        id
+    }
+
+    /// at the start of the sequence, or at a frame index and the seconds already spent on it
+    pub(crate) fn start(
+        &self,
+        name: &str,
+        position: Option<(usize, f32)>,
+    ) -> Result<Playback, &'static str> {
+        let seq = *self.ids.get(name).ok_or("no sequence with that name")?;
+        let (frame, elapsed) = position.unwrap_or((0, 0.0));
+        let sequence = &self.sequences[seq.0 as usize];
+        if frame >= sequence.frames.len() || elapsed.is_nan() || elapsed < 0.0 {
+            return Err("position past the end of the sequence");
+        }
+        Ok(Playback {
+            seq,
+            frame,
+            elapsed: elapsed.min(sequence.frame_time),
+        })
+    }
+
+    /// a sequence that doesn't loop stays on its last frame
+    pub(crate) fn step(&self, playback: &mut Playback, delta_t: f32) {
+        let sequence = &self.sequences[playback.seq.0 as usize];
+        let len = sequence.frames.len();
+        // also catches NaN
+        if !(delta_t > 0.0 && delta_t.is_finite()) {
+            return;
+        }
+        playback.elapsed += delta_t;
+        // whole frames at once, a long hitch doesn't loop over every frame it skipped
+        let frames = (playback.elapsed / sequence.frame_time).floor();
+        if frames < 1.0 {
+            return;
+        }
+        playback.elapsed -= frames * sequence.frame_time;
+        let frames = frames.min(usize::MAX as f32) as usize;
+        playback.frame = if sequence.is_looping {
+            (playback.frame % len + frames % len) % len
+        } else {
+            playback.frame.saturating_add(frames).min(len - 1)
+        };
+    }
+
+    // every frame made from the entry gets what it now resolves to
+    fn refresh(&mut self, tex: &str, entry: (Frame, Rect)) {
+        for sequence in self.sequences.iter_mut() {
+            for (frame, name) in sequence.frames.iter_mut().zip(&sequence.names) {
+                if name == tex {
+                    *frame = entry;
+                }
+            }
+        }
+    }
+
+    /// the atlas frame and its rect, like TextureAtlas::get
+    pub(crate) fn frame(&self, playback: &Playback) -> (Frame, Rect) {
+        let frames = &self.sequences[playback.seq.0 as usize].frames;
+        // the sequence can get fewer frames when it's added again under the same name
+        frames[playback.frame.min(frames.len() - 1)]
+    }
+}
+
+impl Scene {
+    // called when an entry gets marked as packed rotated or not, whatever plays the sequences
+    // picks the new frames up on its next update
+    pub(crate) fn refresh_sequence_frames(&mut self, tex: &str) -> Result<(), &'static str> {
+        let entry = self.atlas_frame(tex)?;
+        self.sequences.refresh(tex, entry);
+        Ok(())
+    }
+
+    /// a sequence to play on sprites, tiles and emitters, `frames` are atlas entries shown `fps`
+    /// times a second
+    pub(crate) fn add_sequence(
+        &mut self,
+        name: &str,
+        frames: &[&str],
+        fps: f32,
+        is_looping: bool,
+    ) -> Result<SequenceId, &'static str> {
+        if frames.is_empty() {
+            return Err("sequence without frames");
+        }
+        // also catches NaN
+        if !(fps > 0.0 && fps.is_finite()) {
+            return Err("sequence fps has to be above 0");
+        }
+        let names: Vec<String> = frames.iter().map(|tex| tex.to_string()).collect();
+        let frames = frames
+            .iter()
+            .map(|tex| self.atlas_frame(tex))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self.sequences.insert(
+            name,
+            Sequence {
+                frames,
+                names,
+                frame_time: 1.0 / fps,
+                is_looping,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sequences(is_looping: bool) -> Sequences {
+        let mut sequences = Sequences::default();
+        let frames = (0..4)
+            .map(|index| {
+                let u = index as f32 * 0.25;
+                let rect = Rect::new_raw(0.0, 0.0, 0.0, 16.0, 16.0);
+                (Frame::from_uv(u, 0.0, u + 0.25, 1.0), rect)
+            })
+            .collect();
+        let names = ["a", "b", "a", "c"].map(|name| name.to_string()).to_vec();
+        sequences.insert(
+            "walk",
+            Sequence {
+                frames,
+                names,
+                frame_time: 0.1,
+                is_looping,
+            },
+        );
+        sequences
+    }
+
+    #[test]
+    fn steps_whole_frames_and_loops() {
+        let sequences = sequences(true);
+        let mut playback = sequences.start("walk", None).unwrap();
+        sequences.step(&mut playback, 0.05);
+        assert_eq!(playback.position().0, 0);
+        sequences.step(&mut playback, 0.1);
+        assert_eq!(playback.position().0, 1);
+        // around past the last frame
+        sequences.step(&mut playback, 0.3);
+        assert_eq!(playback.position().0, 0);
+        assert!((playback.position().1 - 0.05).abs() < 1e-4);
+    }
+
+    #[test]
+    fn holds_the_last_frame_without_looping() {
+        let sequences = sequences(false);
+        let mut playback = sequences.start("walk", Some((2, 0.0))).unwrap();
+        sequences.step(&mut playback, 10.0);
+        assert_eq!(playback.position().0, 3);
+        sequences.step(&mut playback, 0.1);
+        assert_eq!(playback.position().0, 3);
+    }
+
+    #[test]
+    fn start_checks_the_name_and_position() {
+        let sequences = sequences(true);
+        assert!(sequences.start("run", None).is_err());
+        assert!(sequences.start("walk", Some((4, 0.0))).is_err());
+        let playback = sequences.start("walk", Some((3, 0.05))).unwrap();
+        assert_eq!(playback.position(), (3, 0.05));
+    }
+
+    #[test]
+    fn refresh_replaces_every_frame_of_the_entry() {
+        let mut sequences = sequences(true);
+        let rect = Rect::new_raw(0.0, 0.0, 0.0, 32.0, 8.0);
+        sequences.refresh("a", (Frame::from_uv(0.0, 0.0, 1.0, 1.0), rect));
+        let mut playback = sequences.start("walk", None).unwrap();
+        let width = |playback: &Playback| sequences.frame(playback).1.width();
+        assert_eq!(width(&playback), 32.0);
+        sequences.step(&mut playback, 0.1);
+        assert_eq!(width(&playback), 16.0);
+        sequences.step(&mut playback, 0.1);
+        assert_eq!(width(&playback), 32.0);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::*;
+
+/// borders of a frame that keep their size when the sprite is resized, in pixels of the frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) struct NineSlice {
+    pub(crate) left: f32,
+    pub(crate) top: f32,
+    pub(crate) right: f32,
+    pub(crate) bottom: f32,
+    // repeats the middle of the frame along the edges and in the center instead of stretching it
+    pub(crate) tile_middle: bool,
+}
+
+impl NineSlice {
+    pub(crate) fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Self {
+            left,
+            top,
+            right,
+            bottom,
+            tile_middle: false,
+        }
+    }
+
+    pub(crate) fn tiled(mut self) -> Self {
+        self.tile_middle = true;
+        self
+    }
+}
+
+// one direction of the slicing, s is across the rect and u across the frame
+struct SliceAxis {
+    // borders in world units, shrunk to fit when the rect is smaller than both together
+    start: f32,
+    end: f32,
+    // and in pixels of the frame
+    start_px: f32,
+    end_px: f32,
+    native: f32,
+    len: f32,
+    // length of a middle tile in world units, None when the middle stretches
+    tile: Option<f32>,
+    // how much of the frame a whole tile shows, in uv
+    tile_u: f32,
+}
+
+impl SliceAxis {
+    fn new(start_px: f32, end_px: f32, native: f32, len: f32, tile_middle: bool) -> Self {
+        let fit = if start_px + end_px > len && start_px + end_px > 0.0 {
+            len / (start_px + end_px)
+        } else {
+            1.0
+        };
+        let middle_px = native - start_px - end_px;
+        // the tiles scale with the sprite, the same way the frame would if it wasn't sliced
+        let scale = len / native;
+        Self {
+            start: start_px * fit,
+            end: end_px * fit,
+            start_px,
+            end_px,
+            native,
+            len,
+            // tiles under a pixel would be a quad per pixel anyways
+            tile: (tile_middle && middle_px > 0.0).then(|| (middle_px * scale).max(1.0)),
+            tile_u: middle_px / native,
+        }
+    }
+
+    fn middle_count(&self) -> usize {
+        let middle = self.len - self.start - self.end;
+        match self.tile {
+            Some(tile) if middle > 0.0 => (middle / tile).ceil() as usize,
+            _ => 1,
+        }
+    }
+
+    // (s0, s1, u0, u1) of the start border, then the middles, then the end border
+    fn segment(&self, index: usize) -> (f32, f32, f32, f32) {
+        let middle_end = self.len - self.end;
+        let u_start = self.start_px / self.native;
+        let u_end = (self.native - self.end_px) / self.native;
+        if index == 0 {
+            return (0.0, self.start / self.len, 0.0, u_start);
+        }
+        if index > self.middle_count() {
+            return (middle_end / self.len, 1.0, u_end, 1.0);
+        }
+        match self.tile {
+            None => (self.start / self.len, middle_end / self.len, u_start, u_end),
+            Some(tile) => {
+                let from = self.start + (index - 1) as f32 * tile;
+                let to = (from + tile).min(middle_end);
+                // the last tile gets cut short, not squashed
+                let u1 = u_start + (to - from) / tile * self.tile_u;
+                (from / self.len, to / self.len, u_start, u1)
+            }
+        }
+    }
+
+    fn segments(&self) -> impl Iterator<Item = (f32, f32, f32, f32)> + '_ {
+        (0..self.middle_count() + 2)
+            .map(|index| self.segment(index))
+            .filter(|(s0, s1, _, _)| s1 > s0)
+    }
+}
+
+/// cuts a sprite into its nine pieces, more with a tiled middle, works for rotated rects too
+pub(crate) fn for_each_slice(
+    rect: &Rect,
+    frame: &Frame,
+    native_size: Vector2d,
+    slice: &NineSlice,
+    mut f: impl FnMut(Rect, Frame),
+) {
+    let (width, height) = rect.edge_lengths();
+    if width <= 0.0 || height <= 0.0 || native_size.x <= 0.0 || native_size.y <= 0.0 {
+        return;
+    }
+    let x_axis = SliceAxis::new(
+        slice.left,
+        slice.right,
+        native_size.x,
+        width,
+        slice.tile_middle,
+    );
+    let y_axis = SliceAxis::new(
+        slice.top,
+        slice.bottom,
+        native_size.y,
+        height,
+        slice.tile_middle,
+    );
+    for (t0, t1, v0, v1) in y_axis.segments() {
+        for (s0, s1, u0, u1) in x_axis.segments() {
+            f(
+                rect.sub_rect(s0, t0, s1, t1),
+                frame.sub_frame(u0, v0, u1, v1),
+            );
+        }
+    }
+}
+
+/// an atlas entry the way sprites and buttons take it, the frame along with its insets
+#[derive(Clone, Copy)]
+pub(crate) struct AtlasEntry {
+    pub(crate) frame: Frame,
+    pub(crate) frame_rect: Rect,
+    pub(crate) nine_slice: Option<NineSlice>,
+}
+
+impl Scene {
+    pub(crate) fn atlas_entry(&self, tex: &str) -> Result<AtlasEntry, &'static str> {
+        let (frame, frame_rect) = self.atlas_frame(tex)?;
+        Ok(AtlasEntry {
+            frame,
+            frame_rect,
+            nine_slice: self.slice_insets.get(tex).copied(),
+        })
+    }
+
+    /// every sprite that gets set to this atlas entry from now on is nine sliced with these
+    /// insets, until the sprite sets its own
+    pub(crate) fn set_slice_insets(
+        &mut self,
+        tex: &str,
+        slice: Option<NineSlice>,
+    ) -> Result<(), &'static str> {
+        self.tex_atlas.get(tex)?;
+        match slice {
+            Some(slice) => self.slice_insets.insert(tex.to_string(), slice),
+            None => self.slice_insets.remove(tex),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: &[(f32, f32, f32, f32)], b: &[(f32, f32, f32, f32)]) -> bool {
+        let near = |x: f32, y: f32| (x - y).abs() < 1e-5;
+        a.len() == b.len()
+            && a.iter()
+                .zip(b)
+                .all(|(a, b)| near(a.0, b.0) && near(a.1, b.1) && near(a.2, b.2) && near(a.3, b.3))
+    }
+
+    #[test]
+    fn stretched_middle() {
+        let axis = SliceAxis::new(10.0, 10.0, 30.0, 60.0, false);
+        let segments: Vec<_> = axis.segments().collect();
+        let third = 1.0 / 3.0;
+        let expected = [
+            (0.0, 10.0 / 60.0, 0.0, third),
+            (10.0 / 60.0, 50.0 / 60.0, third, 2.0 * third),
+            (50.0 / 60.0, 1.0, 2.0 * third, 1.0),
+        ];
+        assert!(close(&segments, &expected), "{segments:?}");
+    }
+
+    #[test]
+    fn tiles_scale_with_the_sprite() {
+        // twice the native size, so a tile is 20 long and two of them fill the middle
+        let axis = SliceAxis::new(10.0, 10.0, 30.0, 60.0, true);
+        let segments: Vec<_> = axis.segments().collect();
+        let third = 1.0 / 3.0;
+        let expected = [
+            (0.0, 10.0 / 60.0, 0.0, third),
+            (10.0 / 60.0, 0.5, third, 2.0 * third),
+            (0.5, 50.0 / 60.0, third, 2.0 * third),
+            (50.0 / 60.0, 1.0, 2.0 * third, 1.0),
+        ];
+        assert!(close(&segments, &expected), "{segments:?}");
+
+        // the last tile is cut short along with its uv
+        let axis = SliceAxis::new(10.0, 10.0, 30.0, 50.0, true);
+        let segments: Vec<_> = axis.segments().collect();
+        let tile = 10.0 * 50.0 / 30.0;
+        let cut = (30.0 - tile) / tile * third;
+        assert!(close(
+            &segments[2..3],
+            &[(0.2 + tile / 50.0, 0.8, third, third + cut)]
+        ));
+    }
+}
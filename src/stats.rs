@@ -0,0 +1,77 @@
+use crate::*;
+
+/// what the last Scene::update did and how long it took, times are in milliseconds. counts are
+/// summed over every pass, so a sprite drawn by two viewports counts twice
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct FrameStats {
+    // sprites in the scene
+    pub(crate) sprites: u32,
+    // part map regions looked in
+    pub(crate) regions: u32,
+    // sprites a pass left out, for being outside the view or for their layer, target or
+    // visibility, summed over the passes
+    pub(crate) culled: u32,
+    pub(crate) emitted: u32,
+    // sprites whose sequence got stepped, paused ones don't count
+    pub(crate) animated: u32,
+    // everything below counts tilemaps, particles and text too
+    pub(crate) quads: u32,
+    pub(crate) batches: u32,
+    pub(crate) passes: u32,
+    // times the index buffer had to grow, and how many indices it holds now
+    pub(crate) index_allocations: u32,
+    pub(crate) index_len: u32,
+    pub(crate) anim_ms: f32,
+    pub(crate) tween_ms: f32,
+    pub(crate) particle_ms: f32,
+    // the camera controller and the ui
+    pub(crate) camera_ms: f32,
+    // culling and loading every pass
+    pub(crate) pass_ms: f32,
+    pub(crate) total_ms: f32,
+}
+
+// Instant::now panics on wasm32, there every time stays at 0
+#[derive(Clone, Copy)]
+pub(crate) struct Stopwatch {
+    #[cfg(not(target_family = "wasm"))]
+    start: std::time::Instant,
+}
+
+impl Stopwatch {
+    pub(crate) fn start() -> Self {
+        Self {
+            #[cfg(not(target_family = "wasm"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    // the milliseconds since the start, moving the start up to now for the next phase
+    pub(crate) fn lap(&mut self) -> f32 {
+        #[cfg(not(target_family = "wasm"))]
+        {
+            let now = std::time::Instant::now();
+            let elapsed = now - self.start;
+            self.start = now;
+            elapsed.as_secs_f32() * 1000.0
+        }
+        #[cfg(target_family = "wasm")]
+        0.0
+    }
+
+    pub(crate) fn elapsed_ms(&self) -> f32 {
+        #[cfg(not(target_family = "wasm"))]
+        {
+            self.start.elapsed().as_secs_f32() * 1000.0
+        }
+        #[cfg(target_family = "wasm")]
+        0.0
+    }
+}
+
+impl Scene {
+    pub(crate) fn frame_stats(&self) -> &FrameStats {
+        &self.stats
+    }
+}
@@ -0,0 +1,168 @@
+use crate::*;
+
+// a window the size of the sprite's frame that floats over a bigger region of the atlas,
+// conveyor belts, water, skies and so on
+pub(crate) struct FloatStencil {
+    // the region of the atlas the window can float over
+    bound: Frame,
+    // uv size of the window, the size of the frame it was made from
+    window: (f32, f32),
+    // uv offset of the window from the top left of the bound at `since`
+    offset: (f32, f32),
+    // uv per second
+    velocity: (f32, f32),
+    // scene time the offset was taken at, so nothing needs to be touched every frame
+    since: f32,
+    is_wrapping: bool,
+}
+
+impl FloatStencil {
+    // the window starts wherever the frame currently sits in the bound
+    pub(crate) fn new(
+        bound: Frame,
+        frame: Frame,
+        is_wrapping: bool,
+        now: f32,
+    ) -> Result<Self, &'static str> {
+        let bound_size = uv_size(&bound);
+        let frame_size = uv_size(&frame);
+        // the offset gets wrapped and clamped by these
+        if bound_size.0 <= 0.0 || bound_size.1 <= 0.0 {
+            return Err("float stencil bound has no size");
+        }
+        if frame_size.0 > bound_size.0 || frame_size.1 > bound_size.1 {
+            return Err("frame is bigger than the float stencil bound");
+        }
+        let mut stencil = Self {
+            bound,
+            window: frame_size,
+            offset: (
+                frame.top_left.0 - bound.top_left.0,
+                frame.top_left.1 - bound.top_left.1,
+            ),
+            velocity: (0.0, 0.0),
+            since: now,
+            is_wrapping,
+        };
+        stencil.offset = stencil.offset_at(now);
+        Ok(stencil)
+    }
+
+    pub(crate) fn offset_at(&self, now: f32) -> (f32, f32) {
+        let elapsed = now - self.since;
+        let x = self.offset.0 + self.velocity.0 * elapsed;
+        let y = self.offset.1 + self.velocity.1 * elapsed;
+        let bound_size = uv_size(&self.bound);
+        if self.is_wrapping {
+            (x.rem_euclid(bound_size.0), y.rem_euclid(bound_size.1))
+        } else {
+            (
+                x.clamp(0.0, bound_size.0 - self.window.0),
+                y.clamp(0.0, bound_size.1 - self.window.1),
+            )
+        }
+    }
+
+    pub(crate) fn set_offset(&mut self, x: f32, y: f32, now: f32) {
+        self.offset = (x, y);
+        self.since = now;
+        self.offset = self.offset_at(now);
+    }
+
+    pub(crate) fn set_velocity(&mut self, x: f32, y: f32, now: f32) {
+        self.offset = self.offset_at(now);
+        self.since = now;
+        self.velocity = (x, y);
+    }
+
+    /// how far the window has moved from `frame`, for RenderData::load, None when it crosses the
+    /// edge of the bound and has to be drawn in pieces instead
+    pub(crate) fn scroll(&self, frame: &Frame, now: f32) -> Option<FrameVector2d> {
+        let offset = self.offset_at(now);
+        let bound_size = uv_size(&self.bound);
+        if self.is_wrapping
+            && (offset.0 + self.window.0 > bound_size.0 || offset.1 + self.window.1 > bound_size.1)
+        {
+            return None;
+        }
+        Some(FrameVector2d::new(
+            self.bound.top_left.0 + offset.0 - frame.top_left.0,
+            self.bound.top_left.1 + offset.1 - frame.top_left.1,
+        ))
+    }
+
+    /// the quads to draw for `rect`, a wrapping window that crosses the edge of the bound gets cut
+    /// into up to four pieces
+    pub(crate) fn pieces(&self, rect: &Rect, now: f32) -> ArrayVec<(Rect, Frame), 4> {
+        let offset = self.offset_at(now);
+        let bound_size = uv_size(&self.bound);
+        let columns = segments(
+            offset.0,
+            self.window.0,
+            self.bound.top_left.0,
+            bound_size.0,
+            self.is_wrapping,
+        );
+        let rows = segments(
+            offset.1,
+            self.window.1,
+            self.bound.top_left.1,
+            bound_size.1,
+            self.is_wrapping,
+        );
+
+        let mut pieces = ArrayVec::new();
+        for &(t0, t1, v0, v1) in &rows {
+            for &(s0, s1, u0, u1) in &columns {
+                pieces.push((
+                    rect.sub_rect(s0, t0, s1, t1),
+                    Frame::from_uv(u0, v0, u1, v1),
+                ));
+            }
+        }
+        pieces
+    }
+}
+
+fn uv_size(frame: &Frame) -> (f32, f32) {
+    (
+        frame.bottom_right.0 - frame.top_left.0,
+        frame.bottom_right.1 - frame.top_left.1,
+    )
+}
+
+// (start, end) along the quad paired with (start, end) in uv, along one axis
+fn segments(
+    offset: f32,
+    window: f32,
+    bound_start: f32,
+    bound_len: f32,
+    is_wrapping: bool,
+) -> ArrayVec<(f32, f32, f32, f32), 2> {
+    let mut result = ArrayVec::new();
+    let start = bound_start + offset;
+    if !is_wrapping || offset + window <= bound_len || window <= 0.0 {
+        result.push((0.0, 1.0, start, start + window));
+    } else {
+        let split = (bound_len - offset) / window;
+        result.push((0.0, split, start, bound_start + bound_len));
+        result.push((
+            split,
+            1.0,
+            bound_start,
+            bound_start + offset + window - bound_len,
+        ));
+    }
+    result
+}
+
+impl Frame {
+    pub(crate) fn from_uv(u0: f32, v0: f32, u1: f32, v1: f32) -> Self {
+        Self {
+            top_left: (u0, v0),
+            bottom_left: (u0, v1),
+            bottom_right: (u1, v1),
+            top_right: (u1, v0),
+        }
+    }
+}
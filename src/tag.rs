@@ -0,0 +1,166 @@
+use crate::*;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct TagId(u32);
+
+// tag names get interned once, sprites only hold on to the ids
+#[derive(Default)]
+pub(crate) struct Tags {
+    ids: HashMap<String, TagId>,
+    // indexed by the id
+    names: Vec<String>,
+    members: HashMap<TagId, HashSet<SpriteId>>,
+}
+
+impl Tags {
+    pub(crate) fn intern(&mut self, name: &str) -> TagId {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = TagId(self.names.len() as u32);
+        self.ids.insert(name.to_string(), id);
+        self.names.push(name.to_string());
+        id
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<TagId> {
+        self.ids.get(name).copied()
+    }
+
+    pub(crate) fn name(&self, id: TagId) -> &str {
+        &self.names[id.0 as usize]
+    }
+
+    fn tag(&mut self, data: &mut SpriteData, name: &str) -> TagId {
+        let tag = self.intern(name);
+        if !data.tags.contains(&tag) {
+            data.tags.push(tag);
+        }
+        self.members.entry(tag).or_default().insert(data.id);
+        tag
+    }
+
+    fn untag(&mut self, data: &mut SpriteData, name: &str) {
+        let tag = match self.get(name) {
+            Some(tag) => tag,
+            None => return,
+        };
+        data.tags.retain(|each| *each != tag);
+        if let Some(members) = self.members.get_mut(&tag) {
+            members.remove(&data.id);
+        }
+    }
+
+    fn members(&self, name: &str) -> Vec<SpriteId> {
+        let members = match self.get(name).and_then(|tag| self.members.get(&tag)) {
+            Some(members) => members,
+            None => return vec![],
+        };
+        let mut ids: Vec<SpriteId> = members.iter().copied().collect();
+        ids.sort();
+        ids
+    }
+
+    fn forget(&mut self, id: SpriteId, tags: &[TagId]) {
+        for tag in tags {
+            if let Some(members) = self.members.get_mut(tag) {
+                members.remove(&id);
+            }
+        }
+    }
+}
+
+impl Scene {
+    pub(crate) fn tag_sprite(&mut self, id: SpriteId, tag: &str) -> Result<TagId, &'static str> {
+        let (tl_key, _) = self.sprite_keys.get(&id).ok_or("invalid sprite id")?;
+        let data = match &mut self.part_map[tl_key] {
+            SpriteDataPoint::TopLeft(data) => data,
+            SpriteDataPoint::BottomRight => return Err("shouldn't be br point"),
+        };
+        Ok(self.tags.tag(data, tag))
+    }
+
+    pub(crate) fn untag_sprite(&mut self, id: SpriteId, tag: &str) -> Result<(), &'static str> {
+        let (tl_key, _) = self.sprite_keys.get(&id).ok_or("invalid sprite id")?;
+        let data = match &mut self.part_map[tl_key] {
+            SpriteDataPoint::TopLeft(data) => data,
+            SpriteDataPoint::BottomRight => return Err("shouldn't be br point"),
+        };
+        self.tags.untag(data, tag);
+        Ok(())
+    }
+
+    /// sorted by id, which is the order the sprites were created in
+    pub(crate) fn sprites_with_tag(&self, tag: &str) -> Vec<SpriteId> {
+        self.tags.members(tag)
+    }
+
+    pub(crate) fn has_tag(&self, id: SpriteId, tag: &str) -> bool {
+        match (self.tags.get(tag), self.sprite_data(id)) {
+            (Some(tag), Ok(data)) => data.tags.contains(&tag),
+            _ => false,
+        }
+    }
+
+    // called on removal, the tags stay interned
+    pub(crate) fn forget_tags(&mut self, id: SpriteId, tags: &[TagId]) {
+        self.tags.forget(id, tags);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprite(id: u32) -> SpriteData {
+        let rect = Rect::new_raw(0.0, 0.0, 128.0, 16.0, 16.0);
+        SpriteData::new(
+            SpriteId(id),
+            rect,
+            Frame::from_uv(0.0, 0.0, 1.0, 1.0),
+            &rect,
+        )
+    }
+
+    #[test]
+    fn tagging_twice_keeps_one_entry() {
+        let mut tags = Tags::default();
+        let mut data = sprite(0);
+        let first = tags.tag(&mut data, "enemy");
+        assert_eq!(tags.tag(&mut data, "enemy"), first);
+        assert_eq!(data.tags, vec![first]);
+        assert_eq!(tags.get("enemy"), Some(first));
+        assert_eq!(tags.name(first), "enemy");
+        assert_eq!(tags.members("enemy"), vec![SpriteId(0)]);
+    }
+
+    #[test]
+    fn members_come_back_sorted() {
+        let mut tags = Tags::default();
+        for id in [3, 0, 2] {
+            tags.tag(&mut sprite(id), "enemy");
+        }
+        tags.tag(&mut sprite(1), "player");
+        assert_eq!(
+            tags.members("enemy"),
+            vec![SpriteId(0), SpriteId(2), SpriteId(3)]
+        );
+        assert!(tags.members("boss").is_empty());
+    }
+
+    #[test]
+    fn untag_and_forget_drop_the_member() {
+        let mut tags = Tags::default();
+        let (mut first, mut second) = (sprite(0), sprite(1));
+        let enemy = tags.tag(&mut first, "enemy");
+        tags.tag(&mut second, "enemy");
+        tags.untag(&mut first, "enemy");
+        assert!(first.tags.is_empty());
+        assert_eq!(tags.members("enemy"), vec![SpriteId(1)]);
+        // like remove_sprite does with the tags the sprite had
+        tags.forget(SpriteId(1), &second.tags);
+        assert!(tags.members("enemy").is_empty());
+        // the name stays interned
+        assert_eq!(tags.get("enemy"), Some(enemy));
+    }
+}
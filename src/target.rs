@@ -0,0 +1,194 @@
+use crate::*;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct RenderTargetId(pub(crate) u32);
+
+// an offscreen texture, what its camera sees gets drawn into it before the screen is drawn
+pub(crate) struct RenderTarget {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) camera: Camera,
+    // layers drawn into the target on top of the sprites that render only to it
+    pub(crate) layers: LayerMask,
+    // cached targets only get a pass when marked dirty, for ui panels that rarely change
+    pub(crate) is_cached: bool,
+    pub(crate) dirty: bool,
+}
+
+impl RenderTarget {
+    pub(crate) fn needs_pass(&self) -> bool {
+        !self.is_cached || self.dirty
+    }
+}
+
+// the texture the quads of a batch sample from, either the atlas or a render target
+pub(crate) const ATLAS_TEXTURE: u32 = !0;
+// the passes that draw to the screen, they are always last
+pub(crate) const SCREEN_TARGET: u32 = !0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RenderPass {
+    // SCREEN_TARGET or the id of a render target
+    pub(crate) target: u32,
+    // size of the target texture, 0 for the screen
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) batch_start: u32,
+    pub(crate) batch_len: u32,
+    // x, y, width, height of what the camera of the pass sees in world coords
+    pub(crate) view: [f32; 4],
+    // where on the target it goes, as fractions of it, FULL_VIEWPORT for all of it
+    pub(crate) viewport: [f32; 4],
+}
+
+// which sprites go into a pass
+#[derive(Clone, Copy)]
+pub(crate) enum PassFilter {
+    // the layers the camera sees
+    Screen(LayerMask),
+    Target(RenderTargetId, LayerMask),
+    // the ui, in screen pixels on top of every camera
+    Ui,
+}
+
+impl PassFilter {
+    pub(crate) fn accepts(&self, data: &SpriteData) -> bool {
+        if data.is_screen_space != matches!(self, PassFilter::Ui) {
+            return false;
+        }
+        match (self, data.render_target) {
+            (PassFilter::Screen(layers), target) => {
+                target.is_none() && layers.contains(data.rect.top_left.2)
+            }
+            (PassFilter::Target(id, _), Some(target)) => *id == target,
+            (PassFilter::Target(_, layers), None) => layers.contains(data.rect.top_left.2),
+            (PassFilter::Ui, target) => target.is_none(),
+        }
+    }
+
+    // for things that aren't sprites and only have a layer, like tilemaps, none of which are ui
+    pub(crate) fn accepts_layer(&self, depth: f32) -> bool {
+        match self {
+            PassFilter::Screen(layers) | PassFilter::Target(_, layers) => layers.contains(depth),
+            PassFilter::Ui => false,
+        }
+    }
+
+    pub(crate) fn accepts_text(&self, text: &TextData) -> bool {
+        match self {
+            PassFilter::Ui => text.is_screen_space(),
+            _ => !text.is_screen_space() && self.accepts_layer(text.depth()),
+        }
+    }
+}
+
+/// orders the passes so every target is drawn before the passes that sample it, `samples` holds
+/// the targets each pass reads from. a cycle can't be ordered, whoever closes it gets last frame's
+/// content instead
+pub(crate) fn sort_passes(passes: &mut Vec<RenderPass>, samples: &[Vec<u32>]) {
+    fn visit(
+        index: usize,
+        passes: &[RenderPass],
+        samples: &[Vec<u32>],
+        state: &mut [u8],
+        order: &mut Vec<RenderPass>,
+    ) {
+        // 0 not visited, 1 in progress, 2 done
+        if state[index] != 0 {
+            return;
+        }
+        state[index] = 1;
+        for target in &samples[index] {
+            if let Some(dep) = passes.iter().position(|pass| pass.target == *target) {
+                visit(dep, passes, samples, state, order);
+            }
+        }
+        state[index] = 2;
+        order.push(passes[index]);
+    }
+
+    let mut state = vec![0u8; passes.len()];
+    let mut order = Vec::with_capacity(passes.len());
+    for index in 0..passes.len() {
+        visit(index, passes, samples, &mut state, &mut order);
+    }
+    *passes = order;
+}
+
+impl Scene {
+    pub(crate) fn add_render_target(
+        &mut self,
+        width: u32,
+        height: u32,
+        camera: Camera,
+    ) -> RenderTargetId {
+        let id = RenderTargetId(self.next_render_target_id);
+        self.next_render_target_id += 1;
+        self.render_targets.insert(
+            id,
+            RenderTarget {
+                width,
+                height,
+                camera,
+                layers: LayerMask::none(),
+                is_cached: false,
+                dirty: true,
+            },
+        );
+        id
+    }
+
+    // sprites drawn into a removed target go back to the screen, ones sampling it draw nothing
+    pub(crate) fn remove_render_target(&mut self, id: RenderTargetId) -> Result<(), &'static str> {
+        self.render_targets
+            .remove(&id)
+            .ok_or("invalid render target id")?;
+        for point in self.part_map.raw_map.values_mut().flatten().flatten() {
+            if let SpriteDataPoint::TopLeft(data) = point {
+                if data.render_target == Some(id) {
+                    data.render_target = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn render_target_mut(
+        &mut self,
+        id: RenderTargetId,
+    ) -> Result<&mut RenderTarget, &'static str> {
+        self.render_targets
+            .get_mut(&id)
+            .ok_or("invalid render target id")
+    }
+
+    pub(crate) fn set_render_target_layers(
+        &mut self,
+        id: RenderTargetId,
+        layers: LayerMask,
+    ) -> Result<(), &'static str> {
+        let target = self.render_target_mut(id)?;
+        target.layers = layers;
+        target.dirty = true;
+        Ok(())
+    }
+
+    pub(crate) fn set_render_target_cached(
+        &mut self,
+        id: RenderTargetId,
+        is_cached: bool,
+    ) -> Result<(), &'static str> {
+        self.render_target_mut(id)?.is_cached = is_cached;
+        Ok(())
+    }
+
+    // a cached target gets redrawn on the next update
+    pub(crate) fn mark_render_target_dirty(
+        &mut self,
+        id: RenderTargetId,
+    ) -> Result<(), &'static str> {
+        self.render_target_mut(id)?.dirty = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,870 @@
+use crate::*;
+use serde::Deserialize;
+use std::ops::Range;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct FontId(u32);
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct TextId(u32);
+
+struct Glyph {
+    frame: Frame,
+    // all in font pixels, scaled by the text when laid out
+    size: Vector2d,
+    offset: Vector2d,
+    advance: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FontKind {
+    Bitmap,
+    // distance range in pixels of the atlas, and whether there's one channel or three
+    DistanceField { distance_range: f32, is_msdf: bool },
+}
+
+/// either an angelcode bmfont or a distance field font from msdf-atlas-gen, laid out the same way
+pub(crate) struct Font {
+    kind: FontKind,
+    line_height: f32,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+}
+
+/// what a batch of distance field glyphs is drawn with, one per batch
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TextStyle {
+    // in pixels of the font atlas, the shader turns it into screen pixels from the uv derivatives
+    pub(crate) distance_range: f32,
+    // 0 for a single channel field, 1 for msdf
+    pub(crate) is_msdf: u32,
+    // in pixels of the font atlas, 0 for none
+    pub(crate) outline_width: f32,
+    pub(crate) shadow_softness: f32,
+    pub(crate) shadow_offset: [f32; 2],
+    pub(crate) outline_color: Color,
+    pub(crate) shadow_color: Color,
+}
+
+// the msdf-atlas-gen json layout
+#[derive(Deserialize)]
+struct MsdfFont {
+    atlas: MsdfAtlas,
+    metrics: MsdfMetrics,
+    glyphs: Vec<MsdfGlyph>,
+    #[serde(default)]
+    kerning: Vec<MsdfKerning>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MsdfAtlas {
+    #[serde(rename = "type")]
+    kind: String,
+    distance_range: f32,
+    // pixels per em the glyphs were rendered at
+    size: f32,
+    width: f32,
+    height: f32,
+    #[serde(default)]
+    y_origin: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MsdfMetrics {
+    line_height: f32,
+    ascender: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MsdfGlyph {
+    unicode: u32,
+    advance: f32,
+    // both missing for whitespace
+    plane_bounds: Option<MsdfBounds>,
+    atlas_bounds: Option<MsdfBounds>,
+}
+
+#[derive(Deserialize)]
+struct MsdfBounds {
+    left: f32,
+    bottom: f32,
+    right: f32,
+    top: f32,
+}
+
+#[derive(Deserialize)]
+struct MsdfKerning {
+    unicode1: u32,
+    unicode2: u32,
+    advance: f32,
+}
+
+// a line of the text format or an element of the xml one, both have the same tags and attributes
+type FontEntry = (String, Vec<(String, String)>);
+
+fn text_entries(src: &str) -> Result<Vec<FontEntry>, &'static str> {
+    let mut entries = vec![];
+    for line in src.lines() {
+        let line = line.trim();
+        let tag_len = line.find(char::is_whitespace).unwrap_or(line.len());
+        if tag_len == 0 {
+            continue;
+        }
+        let mut attributes = vec![];
+        let mut rest = line[tag_len..].trim_start();
+        while !rest.is_empty() {
+            let eq = rest.find('=').ok_or("expected = in bmfont attribute")?;
+            let key = rest[..eq].trim().to_string();
+            rest = &rest[eq + 1..];
+            let value = if let Some(quoted) = rest.strip_prefix('"') {
+                let len = quoted.find('"').ok_or("unclosed bmfont string")?;
+                rest = &quoted[len + 1..];
+                &quoted[..len]
+            } else {
+                let len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                let value = &rest[..len];
+                rest = &rest[len..];
+                value
+            };
+            attributes.push((key, value.to_string()));
+            rest = rest.trim_start();
+        }
+        entries.push((line[..tag_len].to_string(), attributes));
+    }
+    Ok(entries)
+}
+
+fn xml_entries(src: &str) -> Result<Vec<FontEntry>, &'static str> {
+    let root = parse_xml(src)?;
+    if root.name != "font" {
+        return Err("bmfont xml root isn't a font");
+    }
+    let mut entries = vec![];
+    for child in root.children {
+        match child.name.as_str() {
+            // the xml format wraps these in a list element
+            "pages" | "chars" | "kernings" => {
+                for item in child.children {
+                    entries.push((item.name, item.attributes));
+                }
+            }
+            _ => entries.push((child.name, child.attributes)),
+        }
+    }
+    Ok(entries)
+}
+
+fn attr(attributes: &[(String, String)], name: &str) -> Result<f32, &'static str> {
+    match attributes.iter().find(|(key, _)| key == name) {
+        Some((_, value)) => value.parse().map_err(|_| "invalid number in bmfont"),
+        None => Ok(0.0),
+    }
+}
+
+fn attr_char(attributes: &[(String, String)], name: &str) -> Result<char, &'static str> {
+    char::from_u32(attr(attributes, name)? as u32).ok_or("invalid char id in bmfont")
+}
+
+impl Font {
+    /// takes either the text or the xml flavour of a .fnt file, `page_frame` finds the atlas frame
+    /// of a page image
+    pub(crate) fn load_bmfont(
+        src: &str,
+        page_frame: impl Fn(&str) -> Result<Frame, &'static str>,
+    ) -> Result<Self, &'static str> {
+        let entries = if src.trim_start().starts_with('<') {
+            xml_entries(src)?
+        } else {
+            text_entries(src)?
+        };
+
+        // the glyphs are in pixels of their page, which can be anywhere in the atlas
+        let mut line_height = 0.0;
+        let (mut page_width, mut page_height) = (0.0, 0.0);
+        let mut pages = HashMap::new();
+        for (tag, attributes) in &entries {
+            match tag.as_str() {
+                "common" => {
+                    line_height = attr(attributes, "lineHeight")?;
+                    page_width = attr(attributes, "scaleW")?;
+                    page_height = attr(attributes, "scaleH")?;
+                }
+                "page" => {
+                    let file = attributes
+                        .iter()
+                        .find(|(key, _)| key == "file")
+                        .ok_or("bmfont page without a file")?;
+                    let frame = page_frame(image_name(&file.1))?;
+                    pages.insert(attr(attributes, "id")? as u32, frame);
+                }
+                _ => {}
+            }
+        }
+        if page_width <= 0.0 || page_height <= 0.0 {
+            return Err("bmfont without a page size");
+        }
+
+        let mut glyphs = HashMap::new();
+        let mut kerning = HashMap::new();
+        for (tag, attributes) in &entries {
+            match tag.as_str() {
+                "char" => {
+                    let page = pages
+                        .get(&(attr(attributes, "page")? as u32))
+                        .ok_or("bmfont char on a missing page")?;
+                    let (x, y) = (attr(attributes, "x")?, attr(attributes, "y")?);
+                    let (width, height) = (attr(attributes, "width")?, attr(attributes, "height")?);
+                    glyphs.insert(
+                        attr_char(attributes, "id")?,
+                        Glyph {
+                            frame: page.sub_frame(
+                                x / page_width,
+                                y / page_height,
+                                (x + width) / page_width,
+                                (y + height) / page_height,
+                            ),
+                            size: Vector2d {
+                                x: width,
+                                y: height,
+                            },
+                            offset: Vector2d {
+                                x: attr(attributes, "xoffset")?,
+                                y: attr(attributes, "yoffset")?,
+                            },
+                            advance: attr(attributes, "xadvance")?,
+                        },
+                    );
+                }
+                "kerning" => {
+                    kerning.insert(
+                        (
+                            attr_char(attributes, "first")?,
+                            attr_char(attributes, "second")?,
+                        ),
+                        attr(attributes, "amount")?,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            kind: FontKind::Bitmap,
+            line_height,
+            glyphs,
+            kerning,
+        })
+    }
+
+    /// takes the json msdf-atlas-gen writes with `-json`, `atlas` is the frame of the image it
+    /// wrote next to it
+    pub(crate) fn load_msdf(json: &str, atlas: Frame) -> Result<Self, &'static str> {
+        let src: MsdfFont = serde_json::from_str(json).map_err(|_| "invalid msdf font json")?;
+        let is_msdf = match src.atlas.kind.as_str() {
+            "sdf" | "psdf" => false,
+            "msdf" | "mtsdf" => true,
+            _ => return Err("unsupported msdf atlas type"),
+        };
+        if src.atlas.width <= 0.0 || src.atlas.height <= 0.0 {
+            return Err("msdf atlas without a size");
+        }
+        // metrics are in ems, turned into pixels at the size the atlas was rendered at so the
+        // glyphs come out the same size as a bmfont of that size would
+        let em = src.atlas.size;
+        // everything gets flipped to y going up from the baseline, which is the default
+        let y_down = src.atlas.y_origin.as_deref() == Some("top");
+        let up = |y: f32| if y_down { -y } else { y };
+        let ascender = up(src.metrics.ascender);
+
+        let mut glyphs = HashMap::new();
+        for glyph in &src.glyphs {
+            let c = char::from_u32(glyph.unicode).ok_or("invalid unicode in msdf font")?;
+            let (frame, size, offset) = match (&glyph.plane_bounds, &glyph.atlas_bounds) {
+                (Some(plane), Some(bounds)) => {
+                    // t goes down from the top of the atlas
+                    let t = |y: f32| {
+                        if y_down {
+                            y / src.atlas.height
+                        } else {
+                            1.0 - y / src.atlas.height
+                        }
+                    };
+                    let frame = atlas.sub_frame(
+                        bounds.left / src.atlas.width,
+                        t(bounds.top),
+                        bounds.right / src.atlas.width,
+                        t(bounds.bottom),
+                    );
+                    let size = Vector2d {
+                        x: (plane.right - plane.left) * em,
+                        y: (up(plane.top) - up(plane.bottom)) * em,
+                    };
+                    let offset = Vector2d {
+                        x: plane.left * em,
+                        y: (ascender - up(plane.top)) * em,
+                    };
+                    (frame, size, offset)
+                }
+                _ => (
+                    Frame::from_uv(0.0, 0.0, 0.0, 0.0),
+                    Vector2d { x: 0.0, y: 0.0 },
+                    Vector2d { x: 0.0, y: 0.0 },
+                ),
+            };
+            glyphs.insert(
+                c,
+                Glyph {
+                    frame,
+                    size,
+                    offset,
+                    advance: glyph.advance * em,
+                },
+            );
+        }
+
+        let mut kerning = HashMap::new();
+        for pair in &src.kerning {
+            let first = char::from_u32(pair.unicode1).ok_or("invalid unicode in msdf font")?;
+            let second = char::from_u32(pair.unicode2).ok_or("invalid unicode in msdf font")?;
+            kerning.insert((first, second), pair.advance * em);
+        }
+
+        Ok(Self {
+            kind: FontKind::DistanceField {
+                distance_range: src.atlas.distance_range,
+                is_msdf,
+            },
+            line_height: src.metrics.line_height * em,
+            glyphs,
+            kerning,
+        })
+    }
+
+    fn kerning(&self, prev: Option<char>, c: char) -> f32 {
+        prev.and_then(|prev| self.kerning.get(&(prev, c)))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    fn advance(&self, prev: Option<char>, c: char) -> f32 {
+        let advance = self
+            .glyphs
+            .get(&c)
+            .map(|glyph| glyph.advance)
+            .unwrap_or(0.0);
+        self.kerning(prev, c) + advance
+    }
+
+    fn measure(&self, chars: &[char]) -> f32 {
+        let mut prev = None;
+        let mut width = 0.0;
+        for c in chars {
+            width += self.advance(prev, *c);
+            prev = Some(*c);
+        }
+        width
+    }
+
+    /// splits into lines as ranges of chars, breaking at spaces when possible and mid word when
+    /// a single word is wider than `max_width`, the newlines and the spaces broken at are left out
+    fn wrap(&self, chars: &[char], max_width: Option<f32>) -> Vec<Range<usize>> {
+        let mut lines = vec![];
+        let mut start = 0;
+        let mut width = 0.0;
+        let mut last_space = None;
+        let mut prev = None;
+        let mut index = 0;
+        while index < chars.len() {
+            let c = chars[index];
+            if c == '\n' {
+                lines.push(start..index);
+                start = index + 1;
+                width = 0.0;
+                last_space = None;
+                prev = None;
+                index += 1;
+                continue;
+            }
+            let advance = self.advance(prev, c);
+            if let Some(max_width) = max_width {
+                if width + advance > max_width && index > start && c != ' ' {
+                    match last_space {
+                        Some(space) => {
+                            lines.push(start..space);
+                            start = space + 1;
+                        }
+                        None => {
+                            lines.push(start..index);
+                            start = index;
+                        }
+                    }
+                    // c gets looked at again against the new line
+                    width = self.measure(&chars[start..index]);
+                    last_space = None;
+                    prev = chars[start..index].last().copied();
+                    continue;
+                }
+            }
+            if c == ' ' {
+                last_space = Some(index);
+            }
+            width += advance;
+            prev = Some(c);
+            index += 1;
+        }
+        lines.push(start..chars.len());
+        lines
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TextAlign {
+    Left,
+    Center,
+    Right,
+}
+
+pub(crate) struct TextData {
+    font: FontId,
+    text: String,
+    // top left of the first line
+    pos: Vector2d,
+    depth: f32,
+    // in world units, None to only break at newlines
+    max_width: Option<f32>,
+    align: TextAlign,
+    // multiplier on the line height of the font
+    line_spacing: f32,
+    scale: f32,
+    color: Color,
+    // ranges of char indices, later ones win where they overlap
+    char_colors: Vec<(Range<usize>, Color)>,
+    // only drawn with distance field fonts, in pixels of the font atlas
+    outline: (f32, Color),
+    shadow: (Vector2d, f32, Color),
+    clip: Option<ClipId>,
+    // ui text, in screen pixels like the ui sprites
+    is_screen_space: bool,
+    // laid out every time something above changes, loaded as is
+    quads: Vec<(Rect, Frame, Color)>,
+    bounds: Rect,
+    // None for bitmap fonts
+    style: Option<TextStyle>,
+}
+
+impl TextData {
+    fn new(font: FontId, text: &str, pos: Vector2d) -> Self {
+        Self {
+            font,
+            text: text.to_string(),
+            pos,
+            depth: 128.0,
+            max_width: None,
+            align: TextAlign::Left,
+            line_spacing: 1.0,
+            scale: 1.0,
+            color: Color::white(),
+            char_colors: vec![],
+            outline: (0.0, Color::new(0.0, 0.0, 0.0, 1.0)),
+            shadow: (
+                Vector2d { x: 0.0, y: 0.0 },
+                0.0,
+                Color::new(0.0, 0.0, 0.0, 0.0),
+            ),
+            clip: None,
+            is_screen_space: false,
+            quads: vec![],
+            bounds: Rect::new_raw(pos.x, pos.y, 128.0, 0.0, 0.0),
+            style: None,
+        }
+    }
+
+    fn color_of(&self, index: usize) -> Color {
+        self.char_colors
+            .iter()
+            .rev()
+            .find(|(range, _)| range.contains(&index))
+            .map(|(_, color)| *color)
+            .unwrap_or(self.color)
+    }
+
+    fn layout(&mut self, font: &Font) {
+        self.style = match font.kind {
+            FontKind::Bitmap => None,
+            FontKind::DistanceField {
+                distance_range,
+                is_msdf,
+            } => Some(TextStyle {
+                distance_range,
+                is_msdf: is_msdf as u32,
+                outline_width: self.outline.0,
+                shadow_softness: self.shadow.1,
+                shadow_offset: [self.shadow.0.x, self.shadow.0.y],
+                outline_color: self.outline.1,
+                shadow_color: self.shadow.2,
+            }),
+        };
+
+        let chars: Vec<char> = self.text.chars().collect();
+        let max_width = self.max_width.map(|width| width / self.scale);
+        let lines = font.wrap(&chars, max_width);
+        // trailing spaces don't count towards alignment
+        let widths: Vec<f32> = lines
+            .iter()
+            .map(|line| {
+                let mut end = line.end;
+                while end > line.start && chars[end - 1] == ' ' {
+                    end -= 1;
+                }
+                font.measure(&chars[line.start..end])
+            })
+            .collect();
+        let block_width = max_width.unwrap_or_else(|| widths.iter().copied().fold(0.0, f32::max));
+
+        self.quads.clear();
+        let (mut left, mut top) = (f32::MAX, f32::MAX);
+        let (mut right, mut bottom) = (f32::MIN, f32::MIN);
+        for (line_index, (line, width)) in lines.iter().zip(&widths).enumerate() {
+            let mut pen_x = match self.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => (block_width - width) * 0.5,
+                TextAlign::Right => block_width - width,
+            };
+            let pen_y = line_index as f32 * font.line_height * self.line_spacing;
+            let mut prev = None;
+            for index in line.clone() {
+                let c = chars[index];
+                pen_x += font.kerning(prev, c);
+                prev = Some(c);
+                let glyph = match font.glyphs.get(&c) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                if glyph.size.x > 0.0 && glyph.size.y > 0.0 {
+                    let x = self.pos.x + (pen_x + glyph.offset.x) * self.scale;
+                    let y = self.pos.y + (pen_y + glyph.offset.y) * self.scale;
+                    let (width, height) = (glyph.size.x * self.scale, glyph.size.y * self.scale);
+                    left = left.min(x);
+                    top = top.min(y);
+                    right = right.max(x + width);
+                    bottom = bottom.max(y + height);
+                    let rect = Rect::new_raw(x, y, self.depth, width, height);
+                    self.quads.push((rect, glyph.frame, self.color_of(index)));
+                }
+                pen_x += glyph.advance;
+            }
+        }
+
+        self.bounds = if self.quads.is_empty() {
+            Rect::new_raw(self.pos.x, self.pos.y, self.depth, 0.0, 0.0)
+        } else {
+            Rect::new_raw(left, top, self.depth, right - left, bottom - top)
+        };
+    }
+
+    /// loads the glyphs if any of them can be seen from `view`
+    pub(crate) fn load(
+        &self,
+        render_data: &mut RenderData,
+        view: &Rect,
+        clips: &HashMap<ClipId, Clip>,
+    ) {
+        if !self.bounds.overlaps(view) {
+            return;
+        }
+        // glyphs are only ever cut on the cpu, a rotated clip leaves them whole
+        let clip = self
+            .clip
+            .and_then(|id| clips.get(&id))
+            .map(|clip| &clip.rect)
+            .filter(|rect| rect.is_axis_aligned());
+        let start = render_data.sprite_pos.len();
+        for (rect, frame, color) in &self.quads {
+            render_data.load_clipped(*rect, *frame, *color, clip);
+        }
+        render_data
+            .snap_blocks
+            .push(start..render_data.sprite_pos.len());
+    }
+
+    pub(crate) fn set_text(&mut self, text: &str) {
+        self.text.clear();
+        self.text.push_str(text);
+    }
+
+    // everything a ui widget needs to change, the rest goes through Text
+    pub(crate) fn set_placement(&mut self, pos: Vector2d, scale: f32, depth: f32) {
+        self.pos = pos;
+        self.scale = scale;
+        self.depth = depth;
+    }
+
+    pub(crate) fn set_clip(&mut self, clip: Option<ClipId>) {
+        self.clip = clip;
+    }
+
+    pub(crate) fn set_screen_space(&mut self, is_screen_space: bool) {
+        self.is_screen_space = is_screen_space;
+    }
+
+    pub(crate) fn is_screen_space(&self) -> bool {
+        self.is_screen_space
+    }
+
+    // of the glyphs as laid out
+    pub(crate) fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    pub(crate) fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub(crate) fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    pub(crate) fn style(&self) -> Option<TextStyle> {
+        self.style
+    }
+}
+
+// same deal as Sprite, the text goes away with its handle
+pub(crate) struct Text {
+    scene: *mut Scene,
+    id: TextId,
+}
+
+impl Text {
+    fn read_data_mut<'b>(&mut self) -> &'b mut TextData {
+        unsafe { (*self.scene).texts.get_mut(&self.id).unwrap() }
+    }
+
+    fn layout(&mut self) {
+        unsafe { (*self.scene).layout_text(self.id).unwrap() }
+    }
+
+    pub(crate) fn id(&self) -> TextId {
+        self.id
+    }
+
+    pub(crate) fn set_text(&mut self, text: &str) {
+        self.read_data_mut().set_text(text);
+        self.layout();
+    }
+
+    pub(crate) fn set_font(&mut self, font: FontId) -> Result<(), &'static str> {
+        if unsafe { !(*self.scene).fonts.contains_key(&font) } {
+            return Err("invalid font id");
+        }
+        self.read_data_mut().font = font;
+        self.layout();
+        Ok(())
+    }
+
+    // top left of the first line
+    pub(crate) fn set_pos(&mut self, x: f32, y: f32) {
+        self.read_data_mut().pos = Vector2d { x, y };
+        self.layout();
+    }
+
+    pub(crate) fn set_layer(&mut self, layer: u8, is_ui: bool) {
+        self.read_data_mut().depth = if is_ui {
+            layer as f32
+        } else {
+            layer as f32 + 128.0
+        };
+        self.layout();
+    }
+
+    // lines wrap at spaces to fit in width, None to only break at newlines
+    pub(crate) fn set_max_width(&mut self, width: Option<f32>) {
+        self.read_data_mut().max_width = width;
+        self.layout();
+    }
+
+    // lines are aligned within the max width, or the widest line without one
+    pub(crate) fn set_align(&mut self, align: TextAlign) {
+        self.read_data_mut().align = align;
+        self.layout();
+    }
+
+    // 1 is the line height of the font
+    pub(crate) fn set_line_spacing(&mut self, spacing: f32) {
+        self.read_data_mut().line_spacing = spacing;
+        self.layout();
+    }
+
+    // 1 draws the glyphs at the pixel size of the font
+    pub(crate) fn set_scale(&mut self, scale: f32) {
+        self.read_data_mut().scale = scale;
+        self.layout();
+    }
+
+    pub(crate) fn set_color(&mut self, color: Color) {
+        self.read_data_mut().color = color;
+        self.layout();
+    }
+
+    // chars are counted, not bytes
+    pub(crate) fn set_char_color(&mut self, chars: Range<usize>, color: Color) {
+        self.read_data_mut().char_colors.push((chars, color));
+        self.layout();
+    }
+
+    pub(crate) fn clear_char_colors(&mut self) {
+        self.read_data_mut().char_colors.clear();
+        self.layout();
+    }
+
+    // does nothing with a bitmap font, 0 width for none
+    pub(crate) fn set_outline(&mut self, width: f32, color: Color) {
+        self.read_data_mut().outline = (width, color);
+        self.layout();
+    }
+
+    // does nothing with a bitmap font, a transparent color for none
+    pub(crate) fn set_shadow(&mut self, offset_x: f32, offset_y: f32, softness: f32, color: Color) {
+        self.read_data_mut().shadow = (
+            Vector2d {
+                x: offset_x,
+                y: offset_y,
+            },
+            softness,
+            color,
+        );
+        self.layout();
+    }
+
+    // same as Sprite::set_clipping_rect, except a rotated clip is ignored
+    pub(crate) fn set_clipping_rect(&mut self, clip: Option<ClipId>) {
+        self.read_data_mut().clip = clip;
+    }
+
+    // of the glyphs as laid out
+    pub(crate) fn get_bounds(&self) -> Rect {
+        unsafe { (*self.scene).texts[&self.id].bounds }
+    }
+
+    pub(crate) fn remove(self) {
+        drop(self)
+    }
+}
+
+impl Drop for Text {
+    fn drop(&mut self) {
+        unsafe {
+            (*self.scene).texts.remove(&self.id);
+        }
+    }
+}
+
+impl Scene {
+    pub(crate) fn load_bmfont(&mut self, src: &str) -> Result<FontId, &'static str> {
+        let font = Font::load_bmfont(src, |page| Ok(self.atlas_frame(page)?.0))?;
+        let id = FontId(self.next_font_id);
+        self.next_font_id += 1;
+        self.fonts.insert(id, font);
+        Ok(id)
+    }
+
+    /// `tex` is the atlas entry of the image msdf-atlas-gen wrote along with the json
+    pub(crate) fn load_msdf_font(&mut self, json: &str, tex: &str) -> Result<FontId, &'static str> {
+        let (frame, _) = self.atlas_frame(tex)?;
+        let font = Font::load_msdf(json, frame)?;
+        let id = FontId(self.next_font_id);
+        self.next_font_id += 1;
+        self.fonts.insert(id, font);
+        Ok(id)
+    }
+
+    pub(crate) fn add_text(
+        &mut self,
+        pos: Vector2d,
+        font: FontId,
+        text: &str,
+    ) -> Result<Text, &'static str> {
+        let id = self.add_text_raw(pos, font, text)?;
+        Ok(Text { scene: self, id })
+    }
+
+    // for text owned by the scene itself, removed by taking it out of texts
+    pub(crate) fn add_text_raw(
+        &mut self,
+        pos: Vector2d,
+        font: FontId,
+        text: &str,
+    ) -> Result<TextId, &'static str> {
+        if !self.fonts.contains_key(&font) {
+            return Err("invalid font id");
+        }
+        let id = TextId(self.next_text_id);
+        self.next_text_id += 1;
+        self.texts.insert(id, TextData::new(font, text, pos));
+        self.layout_text(id)?;
+        Ok(id)
+    }
+
+    pub(crate) fn text_data_mut(&mut self, id: TextId) -> Result<&mut TextData, &'static str> {
+        self.texts.get_mut(&id).ok_or("invalid text id")
+    }
+
+    pub(crate) fn layout_text(&mut self, id: TextId) -> Result<(), &'static str> {
+        let text = self.texts.get_mut(&id).ok_or("invalid text id")?;
+        let font = self.fonts.get(&text.font).ok_or("invalid font id")?;
+        text.layout(font);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every char is 10 wide
+    fn mono_font() -> Font {
+        let glyphs = "abcdefghijklmnopqrstuvwxyz "
+            .chars()
+            .map(|c| {
+                let glyph = Glyph {
+                    frame: Frame::from_uv(0.0, 0.0, 1.0, 1.0),
+                    size: Vector2d { x: 10.0, y: 10.0 },
+                    offset: Vector2d { x: 0.0, y: 0.0 },
+                    advance: 10.0,
+                };
+                (c, glyph)
+            })
+            .collect();
+        Font {
+            kind: FontKind::Bitmap,
+            line_height: 12.0,
+            glyphs,
+            kerning: HashMap::new(),
+        }
+    }
+
+    fn wrapped(text: &str, max_width: Option<f32>) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        mono_font()
+            .wrap(&chars, max_width)
+            .into_iter()
+            .map(|line| chars[line].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn wrap_at_spaces() {
+        assert_eq!(wrapped("ab cd ef", Some(50.0)), ["ab cd", "ef"]);
+        assert_eq!(wrapped("ab cd ef", None), ["ab cd ef"]);
+    }
+
+    #[test]
+    fn wrap_long_word_and_newlines() {
+        assert_eq!(wrapped("abcdefg", Some(30.0)), ["abc", "def", "g"]);
+        assert_eq!(wrapped("ab\n\ncd", Some(100.0)), ["ab", "", "cd"]);
+    }
+}
@@ -0,0 +1,834 @@
+use crate::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+// tiled sets this one on top of the flips for hexagonal maps, it means nothing to us
+const ROTATE_HEX: u32 = 0x1000_0000;
+const GID_MASK: u32 = !(FLIP_X | FLIP_Y | FLIP_DIAGONAL | ROTATE_HEX);
+
+// the structs below follow the tiled json format (.tmj), the xml format (.tmx) gets read into
+// the same ones so both go through the same import
+
+#[derive(Deserialize)]
+pub(crate) struct TiledMap {
+    tilewidth: f32,
+    tileheight: f32,
+    #[serde(default)]
+    layers: Vec<TiledLayer>,
+    #[serde(default)]
+    tilesets: Vec<TiledTileset>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize)]
+struct TiledLayer {
+    // tilelayer, objectgroup, imagelayer or group
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    width: u32,
+    #[serde(default = "default_one")]
+    opacity: f32,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default)]
+    offsetx: f32,
+    #[serde(default)]
+    offsety: f32,
+    #[serde(default = "default_one")]
+    parallaxx: f32,
+    #[serde(default = "default_one")]
+    parallaxy: f32,
+    #[serde(default)]
+    data: Option<TiledData>,
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(default)]
+    compression: Option<String>,
+    // only for infinite maps
+    #[serde(default)]
+    chunks: Vec<TiledChunk>,
+    #[serde(default)]
+    objects: Vec<TiledObject>,
+    // only for groups
+    #[serde(default)]
+    layers: Vec<TiledLayer>,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TiledData {
+    Gids(Vec<u32>),
+    // csv or base64, see the encoding of the layer
+    Encoded(String),
+}
+
+#[derive(Deserialize)]
+struct TiledChunk {
+    x: i32,
+    y: i32,
+    width: u32,
+    data: TiledData,
+}
+
+#[derive(Deserialize)]
+struct TiledObject {
+    #[serde(default)]
+    id: u32,
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type", alias = "class", default)]
+    class: String,
+    #[serde(default)]
+    x: f32,
+    #[serde(default)]
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    // degrees, clockwise
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    gid: Option<u32>,
+    #[serde(default)]
+    point: bool,
+    #[serde(default = "default_true")]
+    visible: bool,
+    #[serde(default)]
+    properties: Vec<TiledProperty>,
+}
+
+#[derive(Deserialize)]
+struct TiledTileset {
+    firstgid: u32,
+    // external tilesets would need file access
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    image: Option<String>,
+    #[serde(default)]
+    imagewidth: f32,
+    #[serde(default)]
+    imageheight: f32,
+    #[serde(default)]
+    tilewidth: f32,
+    #[serde(default)]
+    tileheight: f32,
+    #[serde(default)]
+    spacing: f32,
+    #[serde(default)]
+    margin: f32,
+    #[serde(default)]
+    columns: u32,
+    // only the ones with an image or properties are listed
+    #[serde(default)]
+    tiles: Vec<TiledTile>,
+}
+
+#[derive(Deserialize)]
+struct TiledTile {
+    id: u32,
+    #[serde(default)]
+    image: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TiledProperty {
+    name: String,
+    #[serde(default)]
+    value: Value,
+}
+
+fn default_one() -> f32 {
+    1.0
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// an object from an object layer, the ones with a gid also got a sprite
+pub(crate) struct TiledObjectInfo {
+    pub(crate) id: u32,
+    pub(crate) name: String,
+    pub(crate) class: String,
+    // name of the object layer it came from
+    pub(crate) layer: String,
+    pub(crate) pos: Vector2d,
+    pub(crate) size: Vector2d,
+    pub(crate) rotation: f32,
+    pub(crate) is_point: bool,
+    pub(crate) properties: HashMap<String, Value>,
+}
+
+/// a layer of the map, groups included, in the order they show up in the file
+pub(crate) struct TiledLayerInfo {
+    pub(crate) name: String,
+    // tilelayer, objectgroup, imagelayer or group
+    pub(crate) kind: String,
+    // None for groups, they don't take up a scene layer of their own
+    pub(crate) scene_layer: Option<u8>,
+    pub(crate) properties: HashMap<String, Value>,
+}
+
+/// everything a map turned into, the sprites go away once dropped like any other
+pub(crate) struct TiledImport {
+    // with the name of the tile layer each came from
+    pub(crate) tilemaps: Vec<(String, TilemapId)>,
+    pub(crate) sprites: Vec<(TiledObjectInfo, Sprite)>,
+    pub(crate) markers: Vec<TiledObjectInfo>,
+    pub(crate) layers: Vec<TiledLayerInfo>,
+    pub(crate) properties: HashMap<String, Value>,
+}
+
+// what a group passes down to the layers in it
+#[derive(Clone, Copy)]
+struct Inherited {
+    opacity: f32,
+    visible: bool,
+    offset: Vector2d,
+    parallax: Vector2d,
+}
+
+fn properties_to_map(properties: &[TiledProperty]) -> HashMap<String, Value> {
+    properties
+        .iter()
+        .map(|property| (property.name.clone(), property.value.clone()))
+        .collect()
+}
+
+// atlas entries are named after the image file, without folders or extension
+pub(crate) fn image_name(path: &str) -> &str {
+    let file = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    match file.rfind('.') {
+        Some(dot) if dot > 0 => &file[..dot],
+        _ => file,
+    }
+}
+
+fn decode_base64(src: &str) -> Result<Vec<u8>, &'static str> {
+    let mut result = Vec::with_capacity(src.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for byte in src.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return Err("invalid base64 in layer data"),
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Ok(result)
+}
+
+impl TiledData {
+    fn gids(
+        &self,
+        encoding: Option<&str>,
+        compression: Option<&str>,
+    ) -> Result<Vec<u32>, &'static str> {
+        match self {
+            TiledData::Gids(gids) => Ok(gids.clone()),
+            TiledData::Encoded(text) => match encoding {
+                Some("base64") => {
+                    if compression.is_some_and(|compression| !compression.is_empty()) {
+                        return Err(
+                            "compressed layer data isn't supported, save the map uncompressed",
+                        );
+                    }
+                    let bytes = decode_base64(text)?;
+                    Ok(bytes
+                        .chunks_exact(4)
+                        .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                        .collect())
+                }
+                Some("csv") | None => text
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|gid| !gid.is_empty())
+                    .map(|gid| gid.parse().map_err(|_| "invalid csv in layer data"))
+                    .collect(),
+                Some(_) => Err("unknown layer data encoding"),
+            },
+        }
+    }
+}
+
+impl TiledMap {
+    pub(crate) fn from_json(json: &str) -> Result<Self, &'static str> {
+        serde_json::from_str(json).map_err(|_| "invalid tiled json map")
+    }
+
+    fn tileset_of(&self, gid: u32) -> Result<&TiledTileset, &'static str> {
+        self.tilesets
+            .iter()
+            .filter(|tileset| tileset.firstgid <= gid)
+            .max_by_key(|tileset| tileset.firstgid)
+            .ok_or("gid not in any tileset")
+    }
+
+    /// the frame of a gid (flags stripped) and the name of the atlas entry it comes from
+    fn frame_of(&self, atlas: &TextureAtlas, gid: u32) -> Result<(Frame, &str), &'static str> {
+        let tileset = self.tileset_of(gid)?;
+        let local = gid - tileset.firstgid;
+
+        // collection of images, every tile is its own atlas entry
+        if let Some(tile) = tileset.tiles.iter().find(|tile| tile.id == local) {
+            if let Some(image) = &tile.image {
+                let name = image_name(image);
+                return Ok((atlas.get(name)?.0, name));
+            }
+        }
+
+        // one image cut into a grid, the whole image is the atlas entry
+        let image = tileset.image.as_deref().ok_or("tile has no image")?;
+        let name = image_name(image);
+        let (sheet, _) = atlas.get(name)?;
+        if tileset.columns == 0 || tileset.imagewidth <= 0.0 || tileset.imageheight <= 0.0 {
+            return Err("tileset is missing its grid size");
+        }
+        let x = tileset.margin
+            + (local % tileset.columns) as f32 * (tileset.tilewidth + tileset.spacing);
+        let y = tileset.margin
+            + (local / tileset.columns) as f32 * (tileset.tileheight + tileset.spacing);
+        let frame = sheet.sub_frame(
+            x / tileset.imagewidth,
+            y / tileset.imageheight,
+            (x + tileset.tilewidth) / tileset.imagewidth,
+            (y + tileset.tileheight) / tileset.imageheight,
+        );
+        Ok((frame, name))
+    }
+}
+
+impl Scene {
+    /// loads a tiled json map (.tmj), every leaf layer gets its own scene layer starting at
+    /// `base_layer`. tilesets are looked up in the atlas by the file name of their image
+    pub(crate) fn load_tiled_json(
+        &mut self,
+        json: &str,
+        base_layer: u8,
+    ) -> Result<TiledImport, &'static str> {
+        let map = TiledMap::from_json(json)?;
+        self.load_tiled(&map, base_layer)
+    }
+
+    /// same as load_tiled_json for the xml format (.tmx)
+    pub(crate) fn load_tiled_xml(
+        &mut self,
+        xml: &str,
+        base_layer: u8,
+    ) -> Result<TiledImport, &'static str> {
+        let map = TiledMap::from_xml(xml)?;
+        self.load_tiled(&map, base_layer)
+    }
+
+    pub(crate) fn load_tiled(
+        &mut self,
+        map: &TiledMap,
+        base_layer: u8,
+    ) -> Result<TiledImport, &'static str> {
+        if map.tilesets.iter().any(|tileset| tileset.source.is_some()) {
+            return Err("external tilesets aren't supported, embed them in the map");
+        }
+
+        let mut import = TiledImport {
+            tilemaps: vec![],
+            sprites: vec![],
+            markers: vec![],
+            layers: vec![],
+            properties: properties_to_map(&map.properties),
+        };
+        let inherited = Inherited {
+            opacity: 1.0,
+            visible: true,
+            offset: Vector2d { x: 0.0, y: 0.0 },
+            parallax: Vector2d { x: 1.0, y: 1.0 },
+        };
+        let mut layer = base_layer;
+        for each in &map.layers {
+            if let Err(err) = self.load_tiled_layer(map, each, inherited, &mut layer, &mut import) {
+                // the sprites go away with the import, the tilemaps have to be taken out by hand
+                for (_, id) in &import.tilemaps {
+                    self.remove_tilemap(*id).ok();
+                }
+                return Err(err);
+            }
+        }
+        Ok(import)
+    }
+
+    fn load_tiled_layer(
+        &mut self,
+        map: &TiledMap,
+        layer: &TiledLayer,
+        inherited: Inherited,
+        next_layer: &mut u8,
+        import: &mut TiledImport,
+    ) -> Result<(), &'static str> {
+        let inherited = Inherited {
+            opacity: inherited.opacity * layer.opacity,
+            visible: inherited.visible && layer.visible,
+            offset: inherited.offset
+                + Vector2d {
+                    x: layer.offsetx,
+                    y: layer.offsety,
+                },
+            parallax: Vector2d {
+                x: inherited.parallax.x * layer.parallaxx,
+                y: inherited.parallax.y * layer.parallaxy,
+            },
+        };
+
+        import.layers.push(TiledLayerInfo {
+            name: layer.name.clone(),
+            kind: layer.kind.clone(),
+            scene_layer: (layer.kind != "group").then_some(*next_layer),
+            properties: properties_to_map(&layer.properties),
+        });
+        match layer.kind.as_str() {
+            "group" => {
+                for each in &layer.layers {
+                    self.load_tiled_layer(map, each, inherited, next_layer, import)?;
+                }
+                return Ok(());
+            }
+            "tilelayer" => self.load_tiled_tiles(map, layer, inherited, *next_layer, import)?,
+            "objectgroup" => self.load_tiled_objects(map, layer, inherited, *next_layer, import)?,
+            // image layers would need a sprite the size of the image, not handled yet
+            _ => {}
+        }
+        *next_layer = next_layer
+            .checked_add(1)
+            .filter(|layer| *layer < 128)
+            .ok_or("map has more layers than the scene")?;
+        Ok(())
+    }
+
+    fn load_tiled_tiles(
+        &mut self,
+        map: &TiledMap,
+        layer: &TiledLayer,
+        inherited: Inherited,
+        scene_layer: u8,
+        import: &mut TiledImport,
+    ) -> Result<(), &'static str> {
+        let id = self.add_tilemap(
+            inherited.offset,
+            map.tilewidth,
+            map.tileheight,
+            scene_layer,
+            false,
+        )?;
+        // in the import right away so a failure further down still takes it out
+        import.tilemaps.push((layer.name.clone(), id));
+        let encoding = layer.encoding.as_deref();
+        let compression = layer.compression.as_deref();
+
+        // a finite map has one block of data, an infinite one is made of chunks
+        let mut blocks = vec![];
+        if let Some(data) = &layer.data {
+            blocks.push((0, 0, layer.width, data.gids(encoding, compression)?));
+        }
+        for chunk in &layer.chunks {
+            blocks.push((
+                chunk.x,
+                chunk.y,
+                chunk.width,
+                chunk.data.gids(encoding, compression)?,
+            ));
+        }
+
+        // gids get turned into tile ids of the tilemap as they show up
+        let mut tile_ids: HashMap<u32, u32> = HashMap::new();
+        for (x, y, width, gids) in blocks {
+            if width == 0 {
+                continue;
+            }
+            for (index, raw) in gids.iter().enumerate() {
+                let gid = raw & GID_MASK;
+                if gid == 0 {
+                    continue;
+                }
+                let tile_id = match tile_ids.get(&gid) {
+                    Some(tile_id) => *tile_id,
+                    None => {
+                        let (frame, _) = map.frame_of(&self.tex_atlas, gid)?;
+                        let tile_id = self
+                            .tilemap_mut(id)?
+                            .add_tile_frame(TileFrame::Static(frame));
+                        tile_ids.insert(gid, tile_id);
+                        tile_id
+                    }
+                };
+                let tile = Tile {
+                    id: tile_id,
+                    flip_x: raw & FLIP_X != 0,
+                    flip_y: raw & FLIP_Y != 0,
+                    flip_diagonal: raw & FLIP_DIAGONAL != 0,
+                };
+                let index = index as u32;
+                self.tilemap_mut(id)?.set_tile(
+                    x + (index % width) as i32,
+                    y + (index / width) as i32,
+                    Some(tile),
+                )?;
+            }
+        }
+
+        let tilemap = self.tilemap_mut(id)?;
+        tilemap.tint.a = inherited.opacity;
+        tilemap.visible = inherited.visible;
+        tilemap.parallax = inherited.parallax;
+        Ok(())
+    }
+
+    fn load_tiled_objects(
+        &mut self,
+        map: &TiledMap,
+        layer: &TiledLayer,
+        inherited: Inherited,
+        scene_layer: u8,
+        import: &mut TiledImport,
+    ) -> Result<(), &'static str> {
+        for object in &layer.objects {
+            let info = TiledObjectInfo {
+                id: object.id,
+                name: object.name.clone(),
+                class: object.class.clone(),
+                layer: layer.name.clone(),
+                pos: inherited.offset
+                    + Vector2d {
+                        x: object.x,
+                        y: object.y,
+                    },
+                size: Vector2d {
+                    x: object.width,
+                    y: object.height,
+                },
+                rotation: object.rotation.to_radians(),
+                is_point: object.point,
+                properties: properties_to_map(&object.properties),
+            };
+
+            let raw = match object.gid {
+                Some(raw) if raw & GID_MASK != 0 => raw,
+                _ => {
+                    import.markers.push(info);
+                    continue;
+                }
+            };
+
+            // tile objects sit on their bottom left corner
+            let (frame, name) = map.frame_of(&self.tex_atlas, raw & GID_MASK)?;
+            let top_left = info.pos
+                - Vector2d {
+                    x: 0.0,
+                    y: info.size.y,
+                };
+            let mut sprite = self.add_sprite(top_left, name)?;
+            let frame = frame.flipped(
+                raw & FLIP_X != 0,
+                raw & FLIP_Y != 0,
+                raw & FLIP_DIAGONAL != 0,
+            );
+            let (size, rotation) = (info.size, info.rotation);
+            // objects can sit past the scene bounds, that's an error instead of a panic, and the
+            // sprite goes away when it's dropped on the way out
+            self.transform_sprite(sprite.id(), |data| {
+                let depth = scene_layer as f32 + 128.0;
+                data.rect = Rect::new_raw(top_left.x, top_left.y, depth, size.x, size.y);
+                data.frame = frame;
+                data.native_size = size;
+                data.trim_offset = Vector2d { x: 0.0, y: 0.0 };
+                // tiled rotates around the same corner the object sits on
+                data.origin.set(0.0, size.y);
+                if rotation != 0.0 {
+                    data.set_rotation(rotation);
+                }
+            })?;
+            sprite.set_opacity(inherited.opacity);
+            sprite.set_visible(inherited.visible && object.visible);
+            import.sprites.push((info, sprite));
+        }
+        Ok(())
+    }
+}
+
+impl TiledMap {
+    pub(crate) fn from_xml(xml: &str) -> Result<Self, &'static str> {
+        let root = parse_xml(xml)?;
+        if root.name != "map" {
+            return Err("tmx root isn't a map");
+        }
+        Ok(Self {
+            tilewidth: root.attr_f32("tilewidth", 0.0)?,
+            tileheight: root.attr_f32("tileheight", 0.0)?,
+            layers: xml_layers(&root)?,
+            tilesets: root
+                .children_named("tileset")
+                .map(xml_tileset)
+                .collect::<Result<_, _>>()?,
+            properties: xml_properties(&root)?,
+        })
+    }
+}
+
+fn xml_properties(element: &XmlElement) -> Result<Vec<TiledProperty>, &'static str> {
+    let properties = match element.child("properties") {
+        Some(properties) => properties,
+        None => return Ok(vec![]),
+    };
+    properties
+        .children_named("property")
+        .map(|property| {
+            // multiline strings go in the text instead of the value
+            let raw = property.attr("value").unwrap_or(property.text.as_str());
+            let value = match property.attr("type").unwrap_or("string") {
+                "bool" => Value::Bool(raw == "true"),
+                "int" | "object" => {
+                    Value::from(raw.parse::<i64>().map_err(|_| "invalid int property")?)
+                }
+                "float" => Value::from(raw.parse::<f64>().map_err(|_| "invalid float property")?),
+                _ => Value::String(raw.to_string()),
+            };
+            Ok(TiledProperty {
+                name: property.attr("name").unwrap_or("").to_string(),
+                value,
+            })
+        })
+        .collect()
+}
+
+// layers, object groups and groups in the order they appear
+fn xml_layers(parent: &XmlElement) -> Result<Vec<TiledLayer>, &'static str> {
+    let mut layers = vec![];
+    for element in &parent.children {
+        let kind = match element.name.as_str() {
+            "layer" => "tilelayer",
+            "objectgroup" => "objectgroup",
+            "imagelayer" => "imagelayer",
+            "group" => "group",
+            _ => continue,
+        };
+        let mut layer = TiledLayer {
+            kind: kind.to_string(),
+            name: element.attr("name").unwrap_or("").to_string(),
+            width: element.attr_u32("width", 0)?,
+            opacity: element.attr_f32("opacity", 1.0)?,
+            visible: element.attr_bool("visible", true),
+            offsetx: element.attr_f32("offsetx", 0.0)?,
+            offsety: element.attr_f32("offsety", 0.0)?,
+            parallaxx: element.attr_f32("parallaxx", 1.0)?,
+            parallaxy: element.attr_f32("parallaxy", 1.0)?,
+            data: None,
+            encoding: None,
+            compression: None,
+            chunks: vec![],
+            objects: vec![],
+            layers: xml_layers(element)?,
+            properties: xml_properties(element)?,
+        };
+
+        if let Some(data) = element.child("data") {
+            layer.encoding = data.attr("encoding").map(str::to_string);
+            layer.compression = data.attr("compression").map(str::to_string);
+            let has_chunks = data.child("chunk").is_some();
+            if !has_chunks {
+                layer.data = Some(xml_data(data)?);
+            }
+            for chunk in data.children_named("chunk") {
+                layer.chunks.push(TiledChunk {
+                    x: chunk.attr_f32("x", 0.0)? as i32,
+                    y: chunk.attr_f32("y", 0.0)? as i32,
+                    width: chunk.attr_u32("width", 0)?,
+                    data: xml_data(chunk)?,
+                });
+            }
+        }
+
+        for object in element.children_named("object") {
+            layer.objects.push(TiledObject {
+                id: object.attr_u32("id", 0)?,
+                name: object.attr("name").unwrap_or("").to_string(),
+                class: object
+                    .attr("type")
+                    .or(object.attr("class"))
+                    .unwrap_or("")
+                    .to_string(),
+                x: object.attr_f32("x", 0.0)?,
+                y: object.attr_f32("y", 0.0)?,
+                width: object.attr_f32("width", 0.0)?,
+                height: object.attr_f32("height", 0.0)?,
+                rotation: object.attr_f32("rotation", 0.0)?,
+                gid: object
+                    .attr("gid")
+                    .map(|gid| gid.parse().map_err(|_| "invalid gid"))
+                    .transpose()?,
+                point: object.child("point").is_some(),
+                visible: object.attr_bool("visible", true),
+                properties: xml_properties(object)?,
+            });
+        }
+        layers.push(layer);
+    }
+    Ok(layers)
+}
+
+// either encoded text or one <tile gid=".."/> per cell
+fn xml_data(element: &XmlElement) -> Result<TiledData, &'static str> {
+    if element.attr("encoding").is_some() || element.child("tile").is_none() {
+        return Ok(TiledData::Encoded(element.text.trim().to_string()));
+    }
+    Ok(TiledData::Gids(
+        element
+            .children_named("tile")
+            .map(|tile| tile.attr_u32("gid", 0))
+            .collect::<Result<_, _>>()?,
+    ))
+}
+
+fn xml_tileset(element: &XmlElement) -> Result<TiledTileset, &'static str> {
+    let image = element.child("image");
+    Ok(TiledTileset {
+        firstgid: element.attr_u32("firstgid", 1)?,
+        source: element.attr("source").map(str::to_string),
+        name: element.attr("name").unwrap_or("").to_string(),
+        image: image
+            .and_then(|image| image.attr("source"))
+            .map(str::to_string),
+        imagewidth: image.map_or(Ok(0.0), |image| image.attr_f32("width", 0.0))?,
+        imageheight: image.map_or(Ok(0.0), |image| image.attr_f32("height", 0.0))?,
+        tilewidth: element.attr_f32("tilewidth", 0.0)?,
+        tileheight: element.attr_f32("tileheight", 0.0)?,
+        spacing: element.attr_f32("spacing", 0.0)?,
+        margin: element.attr_f32("margin", 0.0)?,
+        columns: element.attr_u32("columns", 0)?,
+        tiles: element
+            .children_named("tile")
+            .map(|tile| {
+                let image = tile.child("image");
+                Ok(TiledTile {
+                    id: tile.attr_u32("id", 0)?,
+                    image: image
+                        .and_then(|image| image.attr("source"))
+                        .map(str::to_string),
+                })
+            })
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{
+        "tilewidth": 16, "tileheight": 16,
+        "properties": [{"name": "music", "type": "string", "value": "cave"}],
+        "tilesets": [{"firstgid": 1, "image": "art/tiles.png", "imagewidth": 64,
+            "imageheight": 64, "tilewidth": 16, "tileheight": 16, "columns": 4}],
+        "layers": [
+            {"type": "group", "name": "world", "offsetx": 8, "layers": [
+                {"type": "tilelayer", "name": "ground", "width": 2,
+                    "properties": [{"name": "solid", "type": "bool", "value": true}],
+                    "data": [1, 0, 2147483651, 4]}
+            ]},
+            {"type": "objectgroup", "name": "spawns", "objects": [
+                {"id": 7, "name": "start", "type": "player", "x": 32, "y": 48, "point": true,
+                    "visible": false}
+            ]}
+        ]
+    }"#;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map tilewidth="16" tileheight="16">
+ <properties>
+  <property name="music" value="cave"/>
+ </properties>
+ <tileset firstgid="1" tilewidth="16" tileheight="16" columns="4">
+  <image source="art/tiles.png" width="64" height="64"/>
+ </tileset>
+ <group name="world" offsetx="8">
+  <layer name="ground" width="2">
+   <properties>
+    <property name="solid" type="bool" value="true"/>
+   </properties>
+   <data encoding="csv">
+1,0,
+2147483651,4
+</data>
+  </layer>
+ </group>
+ <objectgroup name="spawns">
+  <object id="7" name="start" type="player" x="32" y="48" visible="0">
+   <point/>
+  </object>
+ </objectgroup>
+</map>"#;
+
+    fn check_map(map: &TiledMap) {
+        assert_eq!((map.tilewidth, map.tileheight), (16.0, 16.0));
+        assert_eq!(properties_to_map(&map.properties)["music"], "cave");
+        assert_eq!(map.tilesets[0].columns, 4);
+        assert_eq!(
+            map.tilesets[0].image.as_deref().map(image_name),
+            Some("tiles")
+        );
+
+        let group = &map.layers[0];
+        assert_eq!((group.kind.as_str(), group.offsetx), ("group", 8.0));
+        let ground = &group.layers[0];
+        assert_eq!((ground.kind.as_str(), ground.width), ("tilelayer", 2));
+        assert_eq!(properties_to_map(&ground.properties)["solid"], true);
+        let encoding = ground.encoding.as_deref();
+        let gids = ground.data.as_ref().unwrap().gids(encoding, None).unwrap();
+        assert_eq!(gids, [1, 0, FLIP_X | 3, 4]);
+
+        let object = &map.layers[1].objects[0];
+        assert_eq!((object.id, object.class.as_str()), (7, "player"));
+        assert_eq!((object.x, object.y), (32.0, 48.0));
+        assert!(object.point && !object.visible);
+    }
+
+    #[test]
+    fn json_import() {
+        check_map(&TiledMap::from_json(JSON).unwrap());
+    }
+
+    #[test]
+    fn xml_import() {
+        check_map(&TiledMap::from_xml(XML).unwrap());
+    }
+
+    #[test]
+    fn base64_data() {
+        // 1, 0, 0x80000003 little endian
+        let data = TiledData::Encoded("AQAAAAAAAAADAACA".to_string());
+        assert_eq!(data.gids(Some("base64"), None).unwrap(), [1, 0, FLIP_X | 3]);
+        assert!(data.gids(Some("base64"), Some("zlib")).is_err());
+        assert!(TiledData::Encoded("A*".to_string())
+            .gids(Some("base64"), None)
+            .is_err());
+    }
+}
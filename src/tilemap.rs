@@ -0,0 +1,419 @@
+use crate::*;
+
+// tiles per side of a chunk
+pub(crate) const CHUNK_SIZE: usize = 16;
+
+// same bits tiled uses for its gids
+pub(crate) const FLIP_X: u32 = 0x8000_0000;
+pub(crate) const FLIP_Y: u32 = 0x4000_0000;
+pub(crate) const FLIP_DIAGONAL: u32 = 0x2000_0000;
+const TILE_MASK: u32 = !(FLIP_X | FLIP_Y | FLIP_DIAGONAL);
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct TilemapId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Tile {
+    // index into the tile frames of the map
+    pub(crate) id: u32,
+    pub(crate) flip_x: bool,
+    pub(crate) flip_y: bool,
+    // swaps x and y, which together with the flips gives the rotations
+    pub(crate) flip_diagonal: bool,
+}
+
+impl Tile {
+    pub(crate) fn new(id: u32) -> Self {
+        Self {
+            id,
+            flip_x: false,
+            flip_y: false,
+            flip_diagonal: false,
+        }
+    }
+
+    // clockwise
+    pub(crate) fn rotated(id: u32, quarter_turns: u32) -> Self {
+        let (flip_x, flip_y, flip_diagonal) = match quarter_turns % 4 {
+            0 => (false, false, false),
+            1 => (true, false, true),
+            2 => (true, true, false),
+            _ => (false, true, true),
+        };
+        Self {
+            id,
+            flip_x,
+            flip_y,
+            flip_diagonal,
+        }
+    }
+
+    // 0 is an empty cell, None when the id doesn't fit next to the flip bits
+    fn pack(self) -> Option<u32> {
+        let mut raw = self.id.checked_add(1).filter(|raw| raw & !TILE_MASK == 0)?;
+        if self.flip_x {
+            raw |= FLIP_X;
+        }
+        if self.flip_y {
+            raw |= FLIP_Y;
+        }
+        if self.flip_diagonal {
+            raw |= FLIP_DIAGONAL;
+        }
+        Some(raw)
+    }
+
+    fn unpack(raw: u32) -> Option<Self> {
+        if raw & TILE_MASK == 0 {
+            return None;
+        }
+        Some(Self {
+            id: (raw & TILE_MASK) - 1,
+            flip_x: raw & FLIP_X != 0,
+            flip_y: raw & FLIP_Y != 0,
+            flip_diagonal: raw & FLIP_DIAGONAL != 0,
+        })
+    }
+}
+
+impl Frame {
+    // diagonal goes first, same as tiled
+    pub(crate) fn flipped(mut self, flip_x: bool, flip_y: bool, flip_diagonal: bool) -> Self {
+        if flip_diagonal {
+            std::mem::swap(&mut self.top_right, &mut self.bottom_left);
+        }
+        if flip_x {
+            std::mem::swap(&mut self.top_left, &mut self.top_right);
+            std::mem::swap(&mut self.bottom_left, &mut self.bottom_right);
+        }
+        if flip_y {
+            std::mem::swap(&mut self.top_left, &mut self.bottom_left);
+            std::mem::swap(&mut self.top_right, &mut self.bottom_right);
+        }
+        self
+    }
+}
+
+pub(crate) enum TileFrame {
+    Static(Frame),
+    // one playback shared by every tile of this id
+    Animated(Playback),
+}
+
+struct Chunk {
+    tiles: [u32; CHUNK_SIZE * CHUNK_SIZE],
+    // built from the tiles whenever they change, copied straight into the render data
+    sprite_pos: Vec<Rect>,
+    tex_pos: Vec<Frame>,
+    // (index into the quads, tile) of the ones whose frame has to be looked up every frame
+    animated: Vec<(usize, Tile)>,
+    dirty: bool,
+}
+
+impl Chunk {
+    fn new() -> Self {
+        Self {
+            tiles: [0; CHUNK_SIZE * CHUNK_SIZE],
+            sprite_pos: vec![],
+            tex_pos: vec![],
+            animated: vec![],
+            dirty: false,
+        }
+    }
+
+    fn rebuild(&mut self, origin: Vector2d, tile_size: Vector2d, depth: f32, frames: &[TileFrame]) {
+        self.sprite_pos.clear();
+        self.tex_pos.clear();
+        self.animated.clear();
+        for (index, raw) in self.tiles.iter().enumerate() {
+            let tile = match Tile::unpack(*raw) {
+                Some(tile) => tile,
+                None => continue,
+            };
+            let frame = match frames.get(tile.id as usize) {
+                Some(TileFrame::Static(frame)) => {
+                    frame.flipped(tile.flip_x, tile.flip_y, tile.flip_diagonal)
+                }
+                Some(TileFrame::Animated(_)) => {
+                    self.animated.push((self.sprite_pos.len(), tile));
+                    // patched every frame
+                    Frame::from_uv(0.0, 0.0, 0.0, 0.0)
+                }
+                // a tile id that was never added
+                None => continue,
+            };
+            let x = origin.x + (index % CHUNK_SIZE) as f32 * tile_size.x;
+            let y = origin.y + (index / CHUNK_SIZE) as f32 * tile_size.y;
+            self.sprite_pos
+                .push(Rect::new_raw(x, y, depth, tile_size.x, tile_size.y));
+            self.tex_pos.push(frame);
+        }
+        self.dirty = false;
+    }
+}
+
+/// a grid of tiles drawn on one layer, much cheaper than a sprite per tile since only whole
+/// chunks get culled and their quads are built once
+pub(crate) struct Tilemap {
+    // world position of the top left of tile 0, 0
+    pos: Vector2d,
+    tile_size: Vector2d,
+    depth: f32,
+    pub(crate) tint: Color,
+    pub(crate) visible: bool,
+    // 1 moves with the camera, 0 sticks to it, same as tiled's parallax factor
+    pub(crate) parallax: Vector2d,
+    frames: Vec<TileFrame>,
+    chunks: HashMap<(i32, i32), Chunk>,
+}
+
+impl Tilemap {
+    pub(crate) fn new(pos: Vector2d, tile_size: Vector2d, layer: u8, is_ui: bool) -> Self {
+        Self {
+            pos,
+            tile_size,
+            depth: if is_ui {
+                layer as f32
+            } else {
+                layer as f32 + 128.0
+            },
+            tint: Color::white(),
+            visible: true,
+            parallax: Vector2d { x: 1.0, y: 1.0 },
+            frames: vec![],
+            chunks: HashMap::new(),
+        }
+    }
+
+    /// returns the tile id to use with set_tile
+    pub(crate) fn add_tile_frame(&mut self, frame: TileFrame) -> u32 {
+        self.frames.push(frame);
+        self.frames.len() as u32 - 1
+    }
+
+    pub(crate) fn step_animations(&mut self, sequences: &Sequences, delta_t: f32) {
+        for frame in &mut self.frames {
+            if let TileFrame::Animated(playback) = frame {
+                sequences.step(playback, delta_t);
+            }
+        }
+    }
+
+    fn chunk_of(x: i32, y: i32) -> ((i32, i32), usize) {
+        let size = CHUNK_SIZE as i32;
+        let local = (x.rem_euclid(size) + y.rem_euclid(size) * size) as usize;
+        ((x.div_euclid(size), y.div_euclid(size)), local)
+    }
+
+    // None to clear the cell
+    pub(crate) fn set_tile(
+        &mut self,
+        x: i32,
+        y: i32,
+        tile: Option<Tile>,
+    ) -> Result<(), &'static str> {
+        let (key, local) = Self::chunk_of(x, y);
+        let raw = match tile {
+            Some(tile) => tile.pack().ok_or("tile id too big")?,
+            None => 0,
+        };
+        if raw == 0 && !self.chunks.contains_key(&key) {
+            return Ok(());
+        }
+        let chunk = self.chunks.entry(key).or_insert_with(Chunk::new);
+        chunk.tiles[local] = raw;
+        chunk.dirty = true;
+        Ok(())
+    }
+
+    pub(crate) fn get_tile(&self, x: i32, y: i32) -> Option<Tile> {
+        let (key, local) = Self::chunk_of(x, y);
+        Tile::unpack(self.chunks.get(&key)?.tiles[local])
+    }
+
+    // tile coords of the tile under a world position
+    pub(crate) fn tile_at(&self, pos: Vector2d) -> (i32, i32) {
+        (
+            ((pos.x - self.pos.x) / self.tile_size.x).floor() as i32,
+            ((pos.y - self.pos.y) / self.tile_size.y).floor() as i32,
+        )
+    }
+
+    pub(crate) fn depth(&self) -> f32 {
+        self.depth
+    }
+
+    /// loads every chunk `view` overlaps, rebuilding the ones that changed
+    pub(crate) fn load(
+        &mut self,
+        render_data: &mut RenderData,
+        sequences: &Sequences,
+        view: &Rect,
+    ) {
+        if !self.visible {
+            return;
+        }
+        let mut bound = view.get_bounding_rect();
+        // the whole map is shifted by how much less than the camera it's supposed to move
+        let center = bound.center_global();
+        let shift = Vector2d {
+            x: center.x * (1.0 - self.parallax.x),
+            y: center.y * (1.0 - self.parallax.y),
+        };
+        bound.offset_pos(-shift);
+
+        let chunk_width = self.tile_size.x * CHUNK_SIZE as f32;
+        let chunk_height = self.tile_size.y * CHUNK_SIZE as f32;
+        let left = ((bound.top_left().x - self.pos.x) / chunk_width).floor() as i32;
+        let top = ((bound.top_left().y - self.pos.y) / chunk_height).floor() as i32;
+        let right = ((bound.bottom_right().x - self.pos.x) / chunk_width).floor() as i32;
+        let bottom = ((bound.bottom_right().y - self.pos.y) / chunk_height).floor() as i32;
+
+        // only the chunks that exist get looked at, a far zoom out covers a lot of empty ones.
+        // sorted so the draw order doesn't depend on the map
+        let mut keys: Vec<(i32, i32)> = self
+            .chunks
+            .keys()
+            .filter(|(x, y)| (left..=right).contains(x) && (top..=bottom).contains(y))
+            .copied()
+            .collect();
+        keys.sort_unstable_by_key(|(x, y)| (*y, *x));
+
+        for (chunk_x, chunk_y) in keys {
+            let chunk = match self.chunks.get_mut(&(chunk_x, chunk_y)) {
+                Some(chunk) => chunk,
+                None => continue,
+            };
+            if chunk.dirty {
+                let origin = Vector2d {
+                    x: self.pos.x + chunk_x as f32 * chunk_width,
+                    y: self.pos.y + chunk_y as f32 * chunk_height,
+                };
+                chunk.rebuild(origin, self.tile_size, self.depth, &self.frames);
+            }
+
+            let start = render_data.tex_pos.len();
+            render_data.sprite_pos.extend_from_slice(&chunk.sprite_pos);
+            if shift.x != 0.0 || shift.y != 0.0 {
+                for rect in &mut render_data.sprite_pos[start..] {
+                    rect.offset_pos(shift);
+                }
+            }
+            render_data.tex_pos.extend_from_slice(&chunk.tex_pos);
+            render_data
+                .snap_blocks
+                .push(start..render_data.sprite_pos.len());
+            render_data
+                .color
+                .resize(render_data.sprite_pos.len(), [self.tint; 4]);
+            for (index, tile) in &chunk.animated {
+                if let Some(TileFrame::Animated(playback)) = self.frames.get(tile.id as usize) {
+                    let (frame, _) = sequences.frame(playback);
+                    render_data.tex_pos[start + index] =
+                        frame.flipped(tile.flip_x, tile.flip_y, tile.flip_diagonal);
+                }
+            }
+        }
+    }
+}
+
+impl Scene {
+    pub(crate) fn add_tilemap(
+        &mut self,
+        pos: Vector2d,
+        tile_width: f32,
+        tile_height: f32,
+        layer: u8,
+        is_ui: bool,
+    ) -> Result<TilemapId, &'static str> {
+        // also catches NaN
+        if !(tile_width > 0.0 && tile_height > 0.0) {
+            return Err("tile size has to be above 0");
+        }
+        let id = TilemapId(self.next_tilemap_id);
+        self.next_tilemap_id += 1;
+        let tile_size = Vector2d {
+            x: tile_width,
+            y: tile_height,
+        };
+        self.tilemaps
+            .insert(id, Tilemap::new(pos, tile_size, layer, is_ui));
+        Ok(id)
+    }
+
+    pub(crate) fn remove_tilemap(&mut self, id: TilemapId) -> Result<(), &'static str> {
+        self.tilemaps.remove(&id).ok_or("invalid tilemap id")?;
+        Ok(())
+    }
+
+    pub(crate) fn tilemap_mut(&mut self, id: TilemapId) -> Result<&mut Tilemap, &'static str> {
+        self.tilemaps.get_mut(&id).ok_or("invalid tilemap id")
+    }
+
+    /// registers an atlas frame as a tile of the map, returns its tile id
+    pub(crate) fn add_tile(&mut self, id: TilemapId, tex: &str) -> Result<u32, &'static str> {
+        let (frame, _) = self.atlas_frame(tex)?;
+        Ok(self
+            .tilemap_mut(id)?
+            .add_tile_frame(TileFrame::Static(frame)))
+    }
+
+    /// same as add_tile but the frames come from a sequence added with Scene::add_sequence
+    pub(crate) fn add_animated_tile(
+        &mut self,
+        id: TilemapId,
+        seq: &str,
+    ) -> Result<u32, &'static str> {
+        let playback = self.sequences.start(seq, None)?;
+        Ok(self
+            .tilemap_mut(id)?
+            .add_tile_frame(TileFrame::Animated(playback)))
+    }
+
+    pub(crate) fn update_tilemaps(&mut self, delta_t: f32) {
+        for tilemap in self.tilemaps.values_mut() {
+            tilemap.step_animations(&self.sequences, delta_t);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tile_round_trip() {
+        let tile = Tile {
+            id: 41,
+            flip_x: true,
+            flip_y: false,
+            flip_diagonal: true,
+        };
+        let mut tilemap = Tilemap::new(
+            Vector2d { x: 0.0, y: 0.0 },
+            Vector2d { x: 16.0, y: 16.0 },
+            0,
+            false,
+        );
+        tilemap.set_tile(-3, 70, Some(tile)).unwrap();
+        assert_eq!(tilemap.get_tile(-3, 70), Some(tile));
+        tilemap.set_tile(-3, 70, None).unwrap();
+        assert_eq!(tilemap.get_tile(-3, 70), None);
+    }
+
+    #[test]
+    fn tile_id_too_big() {
+        let mut tilemap = Tilemap::new(
+            Vector2d { x: 0.0, y: 0.0 },
+            Vector2d { x: 16.0, y: 16.0 },
+            0,
+            false,
+        );
+        for id in [u32::MAX, TILE_MASK] {
+            assert!(tilemap.set_tile(0, 0, Some(Tile::new(id))).is_err());
+        }
+        assert!(tilemap
+            .set_tile(0, 0, Some(Tile::new(TILE_MASK - 1)))
+            .is_ok());
+    }
+}
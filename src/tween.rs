@@ -0,0 +1,452 @@
+use crate::*;
+
+// t is always 0..1 going in, the result can overshoot for elastic and back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    ElasticIn,
+    ElasticOut,
+    ElasticInOut,
+    BackIn,
+    BackOut,
+    BackInOut,
+    BounceIn,
+    BounceOut,
+    BounceInOut,
+}
+
+impl Easing {
+    pub(crate) fn apply(self, t: f32) -> f32 {
+        // constants are the usual penner ones
+        const BACK: f32 = 1.70158;
+        const BACK_IN_OUT: f32 = BACK * 1.525;
+        const ELASTIC: f32 = (2.0 * PI) / 3.0;
+        const ELASTIC_IN_OUT: f32 = (2.0 * PI) / 4.5;
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(2) / 2.0
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::ElasticIn => {
+                if t <= 0.0 || t >= 1.0 {
+                    t
+                } else {
+                    -(2f32.powf(10.0 * t - 10.0)) * ((t * 10.0 - 10.75) * ELASTIC).sin()
+                }
+            }
+            Easing::ElasticOut => {
+                if t <= 0.0 || t >= 1.0 {
+                    t
+                } else {
+                    2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * ELASTIC).sin() + 1.0
+                }
+            }
+            Easing::ElasticInOut => {
+                if t <= 0.0 || t >= 1.0 {
+                    t
+                } else if t < 0.5 {
+                    -(2f32.powf(20.0 * t - 10.0) * ((20.0 * t - 11.125) * ELASTIC_IN_OUT).sin())
+                        / 2.0
+                } else {
+                    (2f32.powf(-20.0 * t + 10.0) * ((20.0 * t - 11.125) * ELASTIC_IN_OUT).sin())
+                        / 2.0
+                        + 1.0
+                }
+            }
+            Easing::BackIn => (BACK + 1.0) * t * t * t - BACK * t * t,
+            Easing::BackOut => 1.0 + (BACK + 1.0) * (t - 1.0).powi(3) + BACK * (t - 1.0).powi(2),
+            Easing::BackInOut => {
+                if t < 0.5 {
+                    ((2.0 * t).powi(2) * ((BACK_IN_OUT + 1.0) * 2.0 * t - BACK_IN_OUT)) / 2.0
+                } else {
+                    ((2.0 * t - 2.0).powi(2)
+                        * ((BACK_IN_OUT + 1.0) * (t * 2.0 - 2.0) + BACK_IN_OUT)
+                        + 2.0)
+                        / 2.0
+                }
+            }
+            Easing::BounceIn => 1.0 - Easing::BounceOut.apply(1.0 - t),
+            Easing::BounceOut => {
+                const N: f32 = 7.5625;
+                const D: f32 = 2.75;
+                if t < 1.0 / D {
+                    N * t * t
+                } else if t < 2.0 / D {
+                    let t = t - 1.5 / D;
+                    N * t * t + 0.75
+                } else if t < 2.5 / D {
+                    let t = t - 2.25 / D;
+                    N * t * t + 0.9375
+                } else {
+                    let t = t - 2.625 / D;
+                    N * t * t + 0.984375
+                }
+            }
+            Easing::BounceInOut => {
+                if t < 0.5 {
+                    (1.0 - Easing::BounceOut.apply(1.0 - 2.0 * t)) / 2.0
+                } else {
+                    (1.0 + Easing::BounceOut.apply(2.0 * t - 1.0)) / 2.0
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct CubicBezier {
+    pub(crate) start: Vector2d,
+    pub(crate) control_a: Vector2d,
+    pub(crate) control_b: Vector2d,
+    pub(crate) end: Vector2d,
+}
+
+impl CubicBezier {
+    pub(crate) fn point(&self, t: f32) -> Vector2d {
+        let inv = 1.0 - t;
+        self.start * (inv * inv * inv)
+            + self.control_a * (3.0 * inv * inv * t)
+            + self.control_b * (3.0 * inv * t * t)
+            + self.end * (t * t * t)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TweenProperty {
+    // of the origin, same as set_pos
+    Position,
+    Scale,
+    Rotation,
+    // only rgb, alpha is opacity
+    Tint,
+    Opacity,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) enum TweenValue {
+    Scalar(f32),
+    Vec2(Vector2d),
+    Color(Color),
+    // moves the origin along a curve that starts wherever the sprite is
+    Path {
+        control_a: Vector2d,
+        control_b: Vector2d,
+        end: Vector2d,
+    },
+}
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TweenId(u32);
+
+pub(crate) struct Tween {
+    sprite: SpriteId,
+    property: TweenProperty,
+    target: TweenValue,
+    // only known once the tween actually starts, so a sequence picks up where the last one left off
+    start: Option<TweenValue>,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+}
+
+impl Tween {
+    pub(crate) fn new(
+        sprite: SpriteId,
+        property: TweenProperty,
+        target: TweenValue,
+        duration: f32,
+        easing: Easing,
+    ) -> Result<Self, &'static str> {
+        let matches = matches!(
+            (property, target),
+            (TweenProperty::Position, TweenValue::Vec2(_))
+                | (TweenProperty::Position, TweenValue::Path { .. })
+                | (TweenProperty::Scale, TweenValue::Vec2(_))
+                | (TweenProperty::Rotation, TweenValue::Scalar(_))
+                | (TweenProperty::Tint, TweenValue::Color(_))
+                | (TweenProperty::Opacity, TweenValue::Scalar(_))
+        );
+        if !matches {
+            return Err("tween target doesn't match the property");
+        }
+        Ok(Self {
+            sprite,
+            property,
+            target,
+            start: None,
+            duration,
+            elapsed: 0.0,
+            easing,
+        })
+    }
+
+    fn value_at(&self, start: TweenValue, t: f32) -> TweenValue {
+        match (start, self.target) {
+            (TweenValue::Scalar(a), TweenValue::Scalar(b)) => TweenValue::Scalar(a + (b - a) * t),
+            (TweenValue::Vec2(a), TweenValue::Vec2(b)) => TweenValue::Vec2(a + (b - a) * t),
+            (TweenValue::Color(a), TweenValue::Color(b)) => TweenValue::Color(a.lerp(b, t)),
+            (
+                TweenValue::Vec2(start),
+                TweenValue::Path {
+                    control_a,
+                    control_b,
+                    end,
+                },
+            ) => TweenValue::Vec2(
+                CubicBezier {
+                    start,
+                    control_a,
+                    control_b,
+                    end,
+                }
+                .point(t),
+            ),
+            _ => unreachable!("checked in Tween::new"),
+        }
+    }
+
+    /// returns the time left over from delta_t once the tween is done
+    fn step(&mut self, scene: &mut Scene, delta_t: f32) -> Result<Option<f32>, &'static str> {
+        let start = match self.start {
+            Some(start) => start,
+            None => {
+                let start = scene.read_tween_value(self.sprite, self.property)?;
+                self.start = Some(start);
+                start
+            }
+        };
+
+        self.elapsed += delta_t;
+        let progress = if self.duration > 0.0 {
+            (self.elapsed / self.duration).min(1.0)
+        } else {
+            1.0
+        };
+        let value = self.value_at(start, self.easing.apply(progress));
+        scene.write_tween_value(self.sprite, self.property, value)?;
+
+        if self.elapsed >= self.duration {
+            Ok(Some(self.elapsed - self.duration))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+pub(crate) enum TweenGroup {
+    Single(Tween),
+    // one after another, each starts from where the previous one ended
+    Sequence {
+        steps: Vec<TweenGroup>,
+        current: usize,
+    },
+    // all at once, done when the longest one is done
+    Parallel(Vec<TweenGroup>),
+}
+
+impl TweenGroup {
+    pub(crate) fn single(
+        sprite: &Sprite,
+        property: TweenProperty,
+        target: TweenValue,
+        duration: f32,
+        easing: Easing,
+    ) -> Result<Self, &'static str> {
+        Ok(TweenGroup::Single(Tween::new(
+            sprite.id(),
+            property,
+            target,
+            duration,
+            easing,
+        )?))
+    }
+
+    pub(crate) fn sequence(steps: Vec<TweenGroup>) -> Self {
+        TweenGroup::Sequence { steps, current: 0 }
+    }
+
+    pub(crate) fn parallel(members: Vec<TweenGroup>) -> Self {
+        TweenGroup::Parallel(members)
+    }
+
+    fn references(&self, sprite: SpriteId) -> bool {
+        match self {
+            TweenGroup::Single(tween) => tween.sprite == sprite,
+            TweenGroup::Sequence { steps, .. } => steps.iter().any(|each| each.references(sprite)),
+            TweenGroup::Parallel(members) => members.iter().any(|each| each.references(sprite)),
+        }
+    }
+
+    fn step(&mut self, scene: &mut Scene, delta_t: f32) -> Result<Option<f32>, &'static str> {
+        match self {
+            TweenGroup::Single(tween) => tween.step(scene, delta_t),
+            TweenGroup::Sequence { steps, current } => {
+                let mut delta_t = delta_t;
+                while *current < steps.len() {
+                    match steps[*current].step(scene, delta_t)? {
+                        Some(left_over) => {
+                            *current += 1;
+                            delta_t = left_over;
+                        }
+                        None => return Ok(None),
+                    }
+                }
+                Ok(Some(delta_t))
+            }
+            TweenGroup::Parallel(members) => {
+                let mut left_over = delta_t;
+                let mut result = Ok(());
+                members.retain_mut(|each| match each.step(scene, delta_t) {
+                    Ok(Some(left)) => {
+                        left_over = left_over.min(left);
+                        false
+                    }
+                    Ok(None) => true,
+                    Err(err) => {
+                        result = Err(err);
+                        true
+                    }
+                });
+                result?;
+                if members.is_empty() {
+                    Ok(Some(left_over))
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct TweenTable {
+    active: Vec<(TweenId, TweenGroup)>,
+    next_id: u32,
+    pub(crate) finished: Vec<TweenId>,
+    // tweens that got dropped, along with why
+    pub(crate) failed: Vec<(TweenId, &'static str)>,
+}
+
+impl TweenTable {
+    pub(crate) fn new() -> Self {
+        Self {
+            active: vec![],
+            next_id: 0,
+            finished: vec![],
+            failed: vec![],
+        }
+    }
+
+    pub(crate) fn add(&mut self, group: TweenGroup) -> TweenId {
+        let id = TweenId(self.next_id);
+        self.next_id += 1;
+        self.active.push((id, group));
+        id
+    }
+
+    pub(crate) fn cancel(&mut self, id: TweenId) -> Result<(), &'static str> {
+        let index = self
+            .active
+            .iter()
+            .position(|(each, _)| *each == id)
+            .ok_or("tween not active")?;
+        self.active.remove(index);
+        Ok(())
+    }
+
+    // a removed sprite takes every tween touching it along
+    pub(crate) fn remove_sprite(&mut self, sprite: SpriteId) {
+        self.active.retain(|(_, group)| !group.references(sprite));
+    }
+}
+
+impl Scene {
+    pub(crate) fn update_tweens(&mut self, delta_t: f32) {
+        // taken out so the tweens can mutate the scene they live in
+        let mut active = std::mem::take(&mut self.tweens.active);
+        active.retain_mut(|(id, group)| match group.step(self, delta_t) {
+            Ok(Some(_)) => {
+                self.tweens.finished.push(*id);
+                false
+            }
+            Ok(None) => true,
+            Err(err) => {
+                self.tweens.failed.push((*id, err));
+                false
+            }
+        });
+        self.tweens.active = active;
+    }
+
+    fn read_tween_value(
+        &self,
+        sprite: SpriteId,
+        property: TweenProperty,
+    ) -> Result<TweenValue, &'static str> {
+        let data = self.sprite_data(sprite)?;
+        Ok(match property {
+            TweenProperty::Position => TweenValue::Vec2(data.origin_global()),
+            TweenProperty::Scale => TweenValue::Vec2(data.scale),
+            TweenProperty::Rotation => TweenValue::Scalar(data.rotation),
+            TweenProperty::Tint => TweenValue::Color(data.tint),
+            TweenProperty::Opacity => TweenValue::Scalar(data.tint.a),
+        })
+    }
+
+    fn write_tween_value(
+        &mut self,
+        sprite: SpriteId,
+        property: TweenProperty,
+        value: TweenValue,
+    ) -> Result<(), &'static str> {
+        let data = self.sprite_data_mut(sprite)?;
+        match (property, value) {
+            // a transform that would take the sprite off the map is undone and ends the tween
+            (TweenProperty::Position, TweenValue::Vec2(pos)) => {
+                let before = data.origin_global();
+                self.transform_sprite(sprite, |data| data.set_pos(pos.x, pos.y))?;
+                self.move_attached(sprite, pos - before);
+            }
+            (TweenProperty::Scale, TweenValue::Vec2(scale)) => {
+                let before = data.scale;
+                self.transform_sprite(sprite, |data| data.scale_to(scale))?;
+                let ratio = |to: f32, from: f32| if from != 0.0 { to / from } else { 1.0 };
+                self.scale_attached(sprite, ratio(scale.x, before.x), ratio(scale.y, before.y));
+            }
+            (TweenProperty::Rotation, TweenValue::Scalar(rad)) => {
+                let delta = rad - data.rotation;
+                self.transform_sprite(sprite, |data| data.set_rotation(delta))?;
+                self.rotate_attached(sprite, delta);
+            }
+            (TweenProperty::Tint, TweenValue::Color(color)) => {
+                data.tint.r = color.r;
+                data.tint.g = color.g;
+                data.tint.b = color.b;
+            }
+            (TweenProperty::Opacity, TweenValue::Scalar(opacity)) => data.tint.a = opacity,
+            _ => return Err("tween value doesn't match the property"),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,862 @@
+use crate::*;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct UiId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Row,
+    Column,
+}
+
+// where the children of a container go across its direction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CrossAlign {
+    Start,
+    Center,
+    End,
+    Stretch,
+}
+
+// the same point on the screen and on a root widget, which get put on top of each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    fn factors(self) -> Vector2d {
+        let (x, y) = match self {
+            Anchor::TopLeft => (0.0, 0.0),
+            Anchor::Top => (0.5, 0.0),
+            Anchor::TopRight => (1.0, 0.0),
+            Anchor::Left => (0.0, 0.5),
+            Anchor::Center => (0.5, 0.5),
+            Anchor::Right => (1.0, 0.5),
+            Anchor::BottomLeft => (0.0, 1.0),
+            Anchor::Bottom => (0.5, 1.0),
+            Anchor::BottomRight => (1.0, 1.0),
+        };
+        Vector2d { x, y }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ButtonState {
+    Normal = 0,
+    Hovered = 1,
+    Pressed = 2,
+}
+
+enum WidgetKind {
+    Container,
+    Image,
+    Label,
+    // atlas entries for each ButtonState
+    Button {
+        frames: Box<[AtlasEntry; 3]>,
+        state: ButtonState,
+    },
+    Slider {
+        handle: SpriteId,
+        min: f32,
+        max: f32,
+        value: f32,
+    },
+    // lays its children out like a container but only shows what's inside of it, scrolls along
+    // its direction
+    ScrollView {
+        clip: ClipId,
+        scroll: f32,
+        content: f32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum UiEvent {
+    Clicked(UiId),
+    ValueChanged(UiId, f32),
+    Scrolled(UiId, f32),
+}
+
+/// a node of the ui tree, everything about the layout is in screen pixels
+pub(crate) struct Widget {
+    kind: WidgetKind,
+    parent: Option<UiId>,
+    children: Vec<UiId>,
+    // background, button face or slider track
+    sprite: Option<SpriteId>,
+    text: Option<TextId>,
+    // 0 on an axis to fit the content
+    pub(crate) size: Vector2d,
+    // share of the space left over in the parent that this gets along the parent's direction
+    pub(crate) grow: f32,
+    // for the children
+    pub(crate) direction: Direction,
+    pub(crate) align: CrossAlign,
+    pub(crate) gap: f32,
+    pub(crate) padding: f32,
+    // only used by widgets without a parent, the offset is from the anchor point of the screen
+    pub(crate) anchor: Anchor,
+    pub(crate) offset: Vector2d,
+    pub(crate) layer: u8,
+    // where the layout put it last
+    pos: Vector2d,
+    extent: Vector2d,
+    depth: f32,
+    // for the slider handle and the button label, over the widget and under the next one
+    top_depth: f32,
+    clip: Option<ClipId>,
+}
+
+pub(crate) struct Ui {
+    widgets: HashMap<UiId, Widget>,
+    next_id: u32,
+    pub(crate) by_sprite: HashMap<SpriteId, UiId>,
+    events: Vec<UiEvent>,
+    // widgets the last layout couldn't place and why
+    failed: Vec<(UiId, &'static str)>,
+    // the layout only runs again when something changed, or the screen size did
+    dirty: bool,
+    placed_for: Option<Vector2d>,
+}
+
+impl Ui {
+    pub(crate) fn new() -> Self {
+        Self {
+            widgets: HashMap::new(),
+            next_id: 0,
+            by_sprite: HashMap::new(),
+            events: vec![],
+            failed: vec![],
+            dirty: false,
+            placed_for: None,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.widgets.is_empty()
+    }
+}
+
+// picks the x or y of a vector by the direction it goes along or across
+fn main_axis(vector: Vector2d, direction: Direction) -> f32 {
+    match direction {
+        Direction::Row => vector.x,
+        Direction::Column => vector.y,
+    }
+}
+
+fn cross_axis(vector: Vector2d, direction: Direction) -> f32 {
+    match direction {
+        Direction::Row => vector.y,
+        Direction::Column => vector.x,
+    }
+}
+
+fn from_axes(main: f32, cross: f32, direction: Direction) -> Vector2d {
+    match direction {
+        Direction::Row => Vector2d { x: main, y: cross },
+        Direction::Column => Vector2d { x: cross, y: main },
+    }
+}
+
+impl Scene {
+    fn add_widget(
+        &mut self,
+        parent: Option<UiId>,
+        kind: WidgetKind,
+        sprite: Option<SpriteId>,
+        text: Option<TextId>,
+    ) -> UiId {
+        let id = UiId(self.ui.next_id);
+        self.ui.next_id += 1;
+        if let Some(parent) = parent {
+            self.ui.widgets.get_mut(&parent).unwrap().children.push(id);
+        }
+        if let Some(sprite) = sprite {
+            self.ui.by_sprite.insert(sprite, id);
+        }
+        if let WidgetKind::Slider { handle, .. } = kind {
+            self.ui.by_sprite.insert(handle, id);
+        }
+        self.ui.widgets.insert(
+            id,
+            Widget {
+                kind,
+                parent,
+                children: vec![],
+                sprite,
+                text,
+                size: Vector2d { x: 0.0, y: 0.0 },
+                grow: 0.0,
+                direction: Direction::Column,
+                align: CrossAlign::Start,
+                gap: 0.0,
+                padding: 0.0,
+                anchor: Anchor::TopLeft,
+                offset: Vector2d { x: 0.0, y: 0.0 },
+                layer: 0,
+                pos: Vector2d { x: 0.0, y: 0.0 },
+                extent: Vector2d { x: 0.0, y: 0.0 },
+                depth: 0.0,
+                top_depth: 0.0,
+                clip: None,
+            },
+        );
+        self.ui.dirty = true;
+        id
+    }
+
+    fn check_ui_parent(&self, parent: Option<UiId>) -> Result<(), &'static str> {
+        match parent.map(|id| self.ui.widgets.get(&id)) {
+            None => Ok(()),
+            Some(None) => Err("invalid ui id"),
+            Some(Some(widget)) => match widget.kind {
+                WidgetKind::Container | WidgetKind::ScrollView { .. } => Ok(()),
+                _ => Err("only containers and scroll views hold other widgets"),
+            },
+        }
+    }
+
+    fn add_ui_sprite(&mut self, tex: &str, is_interactive: bool) -> Result<SpriteId, &'static str> {
+        let sprite = self.add_sprite_raw(self.ui_origin(), tex)?;
+        let data = self.sprite_data_mut(sprite)?;
+        data.is_interactive = is_interactive;
+        data.is_screen_space = true;
+        Ok(sprite)
+    }
+
+    fn add_ui_text(&mut self, font: FontId, text: &str) -> Result<TextId, &'static str> {
+        let text = self.add_text_raw(self.ui_origin(), font, text)?;
+        self.text_data_mut(text)?.set_screen_space(true);
+        Ok(text)
+    }
+
+    // the screen sits in the middle of the part map, so widgets pushed or scrolled past any of
+    // its edges still have somewhere to go, as far as the map has room around the screen
+    pub(crate) fn ui_origin(&self) -> Vector2d {
+        let (size, screen) = (self.part_map.size as f32, self.screen_extent());
+        Vector2d {
+            x: ((size - screen.x) * 0.5).max(0.0),
+            y: ((size - screen.y) * 0.5).max(0.0),
+        }
+    }
+
+    // what the ui pass sees, the screen in pixels
+    pub(crate) fn ui_view(&self) -> Rect {
+        let (origin, screen) = (self.ui_origin(), self.screen_extent());
+        Rect::new_raw(origin.x, origin.y, 0.0, screen.x, screen.y)
+    }
+
+    /// a widget that only lays out its children, `background` is stretched over all of it
+    pub(crate) fn ui_add_container(
+        &mut self,
+        parent: Option<UiId>,
+        background: Option<&str>,
+    ) -> Result<UiId, &'static str> {
+        self.check_ui_parent(parent)?;
+        let sprite = background
+            .map(|tex| self.add_ui_sprite(tex, false))
+            .transpose()?;
+        Ok(self.add_widget(parent, WidgetKind::Container, sprite, None))
+    }
+
+    pub(crate) fn ui_add_scroll_view(
+        &mut self,
+        parent: Option<UiId>,
+        background: Option<&str>,
+    ) -> Result<UiId, &'static str> {
+        self.check_ui_parent(parent)?;
+        let sprite = background
+            .map(|tex| self.add_ui_sprite(tex, false))
+            .transpose()?;
+        let clip = self.add_clip(0.0, 0.0, 0.0, 0.0);
+        let kind = WidgetKind::ScrollView {
+            clip,
+            scroll: 0.0,
+            content: 0.0,
+        };
+        Ok(self.add_widget(parent, kind, sprite, None))
+    }
+
+    pub(crate) fn ui_add_image(
+        &mut self,
+        parent: Option<UiId>,
+        tex: &str,
+    ) -> Result<UiId, &'static str> {
+        self.check_ui_parent(parent)?;
+        let sprite = self.add_ui_sprite(tex, false)?;
+        Ok(self.add_widget(parent, WidgetKind::Image, Some(sprite), None))
+    }
+
+    pub(crate) fn ui_add_label(
+        &mut self,
+        parent: Option<UiId>,
+        font: FontId,
+        text: &str,
+    ) -> Result<UiId, &'static str> {
+        self.check_ui_parent(parent)?;
+        let text = self.add_ui_text(font, text)?;
+        Ok(self.add_widget(parent, WidgetKind::Label, None, Some(text)))
+    }
+
+    /// the frames are atlas entries for each state, the label goes in the middle
+    pub(crate) fn ui_add_button(
+        &mut self,
+        parent: Option<UiId>,
+        normal: &str,
+        hovered: &str,
+        pressed: &str,
+        label: Option<(FontId, &str)>,
+    ) -> Result<UiId, &'static str> {
+        self.check_ui_parent(parent)?;
+        let frames = Box::new([
+            self.atlas_entry(normal)?,
+            self.atlas_entry(hovered)?,
+            self.atlas_entry(pressed)?,
+        ]);
+        let text = label
+            .map(|(font, text)| self.add_ui_text(font, text))
+            .transpose()?;
+        let sprite = self.add_ui_sprite(normal, true)?;
+        let kind = WidgetKind::Button {
+            frames,
+            state: ButtonState::Normal,
+        };
+        Ok(self.add_widget(parent, kind, Some(sprite), text))
+    }
+
+    /// a horizontal slider, the handle is as wide as the track is high
+    pub(crate) fn ui_add_slider(
+        &mut self,
+        parent: Option<UiId>,
+        track: &str,
+        handle: &str,
+        min: f32,
+        max: f32,
+        value: f32,
+    ) -> Result<UiId, &'static str> {
+        self.check_ui_parent(parent)?;
+        if min >= max {
+            return Err("slider min has to be below its max");
+        }
+        let sprite = self.add_ui_sprite(track, true)?;
+        let handle = self.add_ui_sprite(handle, true)?;
+        let kind = WidgetKind::Slider {
+            handle,
+            min,
+            max,
+            value: value.clamp(min, max),
+        };
+        Ok(self.add_widget(parent, kind, Some(sprite), None))
+    }
+
+    /// removes the widget along with all of its children
+    pub(crate) fn ui_remove(&mut self, id: UiId) -> Result<(), &'static str> {
+        let widget = self.ui.widgets.remove(&id).ok_or("invalid ui id")?;
+        if let Some(parent) = widget.parent.and_then(|id| self.ui.widgets.get_mut(&id)) {
+            parent.children.retain(|child| *child != id);
+        }
+        for child in widget.children {
+            self.ui_remove(child)?;
+        }
+        let mut sprites = widget.sprite.into_iter().collect::<ArrayVec<SpriteId, 2>>();
+        match widget.kind {
+            WidgetKind::Slider { handle, .. } => sprites.push(handle),
+            WidgetKind::ScrollView { clip, .. } => self.remove_clip(clip)?,
+            _ => {}
+        }
+        for sprite in sprites {
+            self.ui.by_sprite.remove(&sprite);
+            self.remove_sprite_raw(sprite)?;
+        }
+        if let Some(text) = widget.text {
+            self.texts.remove(&text);
+        }
+        self.ui.dirty = true;
+        Ok(())
+    }
+
+    /// for changing the layout properties, which get applied on the next update
+    pub(crate) fn ui_widget_mut(&mut self, id: UiId) -> Result<&mut Widget, &'static str> {
+        self.ui.dirty = true;
+        self.ui.widgets.get_mut(&id).ok_or("invalid ui id")
+    }
+
+    // for labels and buttons
+    pub(crate) fn ui_set_text(&mut self, id: UiId, text: &str) -> Result<(), &'static str> {
+        let widget = self.ui.widgets.get(&id).ok_or("invalid ui id")?;
+        let text_id = widget.text.ok_or("ui widget has no text")?;
+        self.text_data_mut(text_id)?.set_text(text);
+        self.layout_text(text_id)?;
+        self.ui.dirty = true;
+        Ok(())
+    }
+
+    pub(crate) fn ui_slider_value(&self, id: UiId) -> Result<f32, &'static str> {
+        match self.ui.widgets.get(&id).ok_or("invalid ui id")?.kind {
+            WidgetKind::Slider { value, .. } => Ok(value),
+            _ => Err("ui widget isn't a slider"),
+        }
+    }
+
+    // doesn't send a ValueChanged
+    pub(crate) fn ui_set_slider_value(&mut self, id: UiId, new: f32) -> Result<(), &'static str> {
+        match &mut self.ui.widgets.get_mut(&id).ok_or("invalid ui id")?.kind {
+            WidgetKind::Slider {
+                min, max, value, ..
+            } => *value = new.clamp(*min, *max),
+            _ => return Err("ui widget isn't a slider"),
+        }
+        self.ui.dirty = true;
+        Ok(())
+    }
+
+    /// clicks, slider changes and scrolls since the last call
+    pub(crate) fn ui_events(&mut self) -> std::vec::Drain<'_, UiEvent> {
+        self.ui.events.drain(..)
+    }
+
+    // size it would like to be, the content plus padding where it isn't fixed
+    fn measure_widget(&self, id: UiId) -> Vector2d {
+        let widget = &self.ui.widgets[&id];
+        let native = widget
+            .sprite
+            .and_then(|sprite| self.sprite_data(sprite).ok())
+            .map(|data| data.native_size)
+            .unwrap_or(Vector2d { x: 0.0, y: 0.0 });
+        // glyph bounds are in world units at whatever scale the text was placed at last
+        let text = widget
+            .text
+            .and_then(|text| self.texts.get(&text))
+            .map(|text| {
+                let bounds = text.bounds();
+                Vector2d {
+                    x: bounds.width() / text.scale(),
+                    y: bounds.height() / text.scale(),
+                }
+            })
+            .unwrap_or(Vector2d { x: 0.0, y: 0.0 });
+        let padding = Vector2d {
+            x: widget.padding * 2.0,
+            y: widget.padding * 2.0,
+        };
+
+        let content = match widget.kind {
+            WidgetKind::Image | WidgetKind::Slider { .. } => native,
+            WidgetKind::Label => text,
+            WidgetKind::Button { .. } => Vector2d {
+                x: native.x.max(text.x + padding.x),
+                y: native.y.max(text.y + padding.y),
+            },
+            WidgetKind::Container | WidgetKind::ScrollView { .. } => {
+                let direction = widget.direction;
+                let (mut main, mut cross) = (0.0, 0.0f32);
+                for child in &widget.children {
+                    let size = self.measure_widget(*child);
+                    main += main_axis(size, direction);
+                    cross = cross.max(cross_axis(size, direction));
+                }
+                main += widget.gap * widget.children.len().saturating_sub(1) as f32;
+                from_axes(main, cross, direction) + padding
+            }
+        };
+        Vector2d {
+            x: if widget.size.x > 0.0 {
+                widget.size.x
+            } else {
+                content.x
+            },
+            y: if widget.size.y > 0.0 {
+                widget.size.y
+            } else {
+                content.y
+            },
+        }
+    }
+
+    fn count_widgets(&self, id: UiId) -> usize {
+        let children = &self.ui.widgets[&id].children;
+        1 + children
+            .iter()
+            .map(|child| self.count_widgets(*child))
+            .sum::<usize>()
+    }
+
+    // depths go down by step in tree order, so every widget is drawn over its parent and the
+    // siblings before it, returns the depth for the widget after this one and its children
+    fn arrange_widget(
+        &mut self,
+        id: UiId,
+        pos: Vector2d,
+        extent: Vector2d,
+        (depth, step): (f32, f32),
+        clip: Option<ClipId>,
+    ) -> f32 {
+        let widget = &self.ui.widgets[&id];
+        let children = widget.children.clone();
+        let (direction, align, gap, padding) =
+            (widget.direction, widget.align, widget.gap, widget.padding);
+        let sizes: Vec<Vector2d> = children
+            .iter()
+            .map(|child| self.measure_widget(*child))
+            .collect();
+
+        let inner_pos = pos
+            + Vector2d {
+                x: padding,
+                y: padding,
+            };
+        let inner = Vector2d {
+            x: (extent.x - padding * 2.0).max(0.0),
+            y: (extent.y - padding * 2.0).max(0.0),
+        };
+        let total = sizes
+            .iter()
+            .map(|size| main_axis(*size, direction))
+            .sum::<f32>()
+            + gap * children.len().saturating_sub(1) as f32;
+
+        let widget = self.ui.widgets.get_mut(&id).unwrap();
+        widget.pos = pos;
+        widget.extent = extent;
+        widget.depth = depth;
+        widget.top_depth = depth - step;
+        widget.clip = clip;
+        // scroll views don't grow their children, the space past the view is scrolled to instead
+        let (scroll, child_clip, free) = match &mut widget.kind {
+            WidgetKind::ScrollView {
+                clip: own_clip,
+                scroll,
+                content,
+            } => {
+                *content = total + padding * 2.0;
+                let max_scroll = (*content - main_axis(extent, direction)).max(0.0);
+                *scroll = scroll.clamp(0.0, max_scroll);
+                (*scroll, Some(*own_clip), 0.0)
+            }
+            _ => (0.0, clip, (main_axis(inner, direction) - total).max(0.0)),
+        };
+
+        let grow_total: f32 = children
+            .iter()
+            .map(|child| self.ui.widgets[child].grow)
+            .sum();
+        let inner_cross = cross_axis(inner, direction);
+        let mut cursor = -scroll;
+        let mut next_depth = depth - step * 2.0;
+        for (child, size) in children.iter().zip(sizes) {
+            let grow = self.ui.widgets[child].grow;
+            let main = main_axis(size, direction)
+                + if grow_total > 0.0 {
+                    free * grow / grow_total
+                } else {
+                    0.0
+                };
+            let cross = match align {
+                CrossAlign::Stretch => inner_cross,
+                _ => cross_axis(size, direction),
+            };
+            let cross_offset = match align {
+                CrossAlign::Start | CrossAlign::Stretch => 0.0,
+                CrossAlign::Center => (inner_cross - cross) * 0.5,
+                CrossAlign::End => inner_cross - cross,
+            };
+            let child_pos = inner_pos + from_axes(cursor, cross_offset, direction);
+            let child_extent = from_axes(main, cross, direction);
+            let child_depth = (next_depth, step);
+            next_depth =
+                self.arrange_widget(*child, child_pos, child_extent, child_depth, child_clip);
+            cursor += main + gap;
+        }
+        next_depth
+    }
+
+    // moves the sprites and texts of a widget to where the layout put it, in screen space
+    fn place_widget(&mut self, id: UiId) -> Result<(), &'static str> {
+        let widget = &self.ui.widgets[&id];
+        let (pos, extent, depth, clip) = (widget.pos, widget.extent, widget.depth, widget.clip);
+        let top_depth = widget.top_depth;
+        let (sprite, text) = (widget.sprite, widget.text);
+        let top_left = self.ui_origin() + pos;
+        let rect = Rect::new_raw(top_left.x, top_left.y, depth, extent.x, extent.y);
+
+        let mut handle = None;
+        let mut own_clip = None;
+        match &widget.kind {
+            WidgetKind::ScrollView { clip, .. } => own_clip = Some(*clip),
+            WidgetKind::Slider {
+                handle: handle_sprite,
+                min,
+                max,
+                value,
+            } => {
+                let t = (value - min) / (max - min);
+                let side = rect.height();
+                let x = rect.top_left().x + t * (rect.width() - side).max(0.0);
+                let handle_rect = Rect::new_raw(x, rect.top_left().y, top_depth, side, side);
+                handle = Some((*handle_sprite, handle_rect));
+            }
+            _ => {}
+        }
+        if let Some(own_clip) = own_clip {
+            let (mut start, mut end) = (rect.top_left(), rect.bottom_right());
+            // a scroll view inside another only shows what's inside both, the outer one is
+            // placed first since it was added first
+            if let Some(outer) = clip.and_then(|id| self.clips.get(&id)) {
+                let (outer_start, outer_end) = (outer.rect.top_left(), outer.rect.bottom_right());
+                start = Vector2d {
+                    x: start.x.max(outer_start.x),
+                    y: start.y.max(outer_start.y),
+                };
+                end = Vector2d {
+                    x: end.x.min(outer_end.x).max(start.x),
+                    y: end.y.min(outer_end.y).max(start.y),
+                };
+            }
+            let (width, height) = (end.x - start.x, end.y - start.y);
+            self.set_clip_rect(own_clip, start.x, start.y, width, height)?;
+        }
+
+        for (sprite, rect) in sprite
+            .map(|sprite| (sprite, rect))
+            .into_iter()
+            .chain(handle)
+        {
+            let data = self.sprite_data_mut(sprite)?;
+            data.rect = rect;
+            data.clip = clip;
+            self.update_keys(sprite)?;
+        }
+
+        if let Some(text) = text {
+            // labels sit at the top left, button labels in the middle above the face
+            let is_button = sprite.is_some();
+            let text_depth = if is_button { top_depth } else { depth };
+            let data = self.text_data_mut(text)?;
+            data.set_placement(top_left, 1.0, text_depth);
+            data.set_clip(clip);
+            self.layout_text(text)?;
+            if is_button {
+                let bounds = self.texts[&text].bounds();
+                let offset = rect.center_global() - bounds.center_global();
+                let data = self.text_data_mut(text)?;
+                data.set_placement(top_left + offset, 1.0, text_depth);
+                self.layout_text(text)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// lays out and places every widget when anything about them or the screen size changed,
+    /// called from update. the camera moving doesn't matter, the ui is drawn in screen space
+    pub(crate) fn update_ui(&mut self) {
+        let screen = self.screen_extent();
+        if !self.ui.dirty && self.ui.placed_for == Some(screen) {
+            return;
+        }
+        let mut roots: Vec<UiId> = self
+            .ui
+            .widgets
+            .iter()
+            .filter(|(_, widget)| widget.parent.is_none())
+            .map(|(id, _)| *id)
+            .collect();
+        roots.sort();
+        // the trees on a layer stay within it, a depth of layer + 1 would already be the next
+        // one. roots added later go over the earlier ones
+        let mut per_layer: HashMap<u8, usize> = HashMap::new();
+        for root in roots.iter() {
+            *per_layer.entry(self.ui.widgets[root].layer).or_default() += self.count_widgets(*root);
+        }
+        let mut next_depth: HashMap<u8, (f32, f32)> = per_layer
+            .into_iter()
+            .map(|(layer, count)| {
+                let step = 1.0 / (count * 2 + 1) as f32;
+                (layer, (layer as f32 + 1.0 - step, step))
+            })
+            .collect();
+        for root in roots {
+            let extent = self.measure_widget(root);
+            let widget = &self.ui.widgets[&root];
+            let factors = widget.anchor.factors();
+            let pos = Vector2d {
+                x: (screen.x - extent.x) * factors.x,
+                y: (screen.y - extent.y) * factors.y,
+            } + widget.offset;
+            let (depth, step) = next_depth[&widget.layer];
+            let after = self.arrange_widget(root, pos, extent, (depth, step), None);
+            next_depth.insert(self.ui.widgets[&root].layer, (after, step));
+        }
+
+        let mut ids: Vec<UiId> = self.ui.widgets.keys().copied().collect();
+        ids.sort();
+        for id in ids {
+            // the rest still get placed, this one stays where it was
+            if let Err(err) = self.place_widget(id) {
+                self.ui.failed.push((id, err));
+            }
+        }
+        self.ui.dirty = false;
+        self.ui.placed_for = Some(screen);
+    }
+
+    /// widgets the layout couldn't place since the last call and why, usually from being pushed
+    /// too far off the screen
+    pub(crate) fn failed_widgets(&mut self) -> std::vec::Drain<'_, (UiId, &'static str)> {
+        self.ui.failed.drain(..)
+    }
+
+    fn set_button_state(&mut self, id: UiId, new: ButtonState) {
+        let widget = self.ui.widgets.get_mut(&id).unwrap();
+        let (sprite, entry) = match &mut widget.kind {
+            WidgetKind::Button { frames, state } if *state != new => {
+                *state = new;
+                (widget.sprite.unwrap(), frames[new as usize])
+            }
+            _ => return,
+        };
+        if let Ok(data) = self.sprite_data_mut(sprite) {
+            let rect = data.rect;
+            data.apply_frame(entry.frame, &entry.frame_rect);
+            data.nine_slice = entry.nine_slice;
+            // the layout decides the size, not the frame
+            data.rect = rect;
+        }
+    }
+
+    // `point` is in screen pixels
+    fn set_slider_from(&mut self, id: UiId, point: Vector2d) {
+        let point = point + self.ui_origin();
+        let widget = &self.ui.widgets[&id];
+        let track = match widget
+            .sprite
+            .and_then(|sprite| self.sprite_data(sprite).ok())
+        {
+            Some(data) => data.rect,
+            None => return,
+        };
+        let s = match track.local_coords(point) {
+            Some((s, _)) => s.clamp(0.0, 1.0),
+            None => return,
+        };
+        let widget = self.ui.widgets.get_mut(&id).unwrap();
+        if let WidgetKind::Slider {
+            min, max, value, ..
+        } = &mut widget.kind
+        {
+            let new = *min + (*max - *min) * s;
+            if new != *value {
+                *value = new;
+                self.ui.events.push(UiEvent::ValueChanged(id, new));
+                self.ui.dirty = true;
+            }
+        }
+    }
+
+    /// turns the pointer events of the last pointer call into button states, clicks and slider
+    /// changes
+    pub(crate) fn handle_ui_pointer(&mut self) {
+        for index in 0..self.pointer.events.len() {
+            let event = self.pointer.events[index];
+            let id = match self.ui.by_sprite.get(&event.sprite) {
+                Some(id) => *id,
+                None => continue,
+            };
+            let point = Vector2d {
+                x: event.x,
+                y: event.y,
+            };
+            let is_pressed = self.pointer.pressed == Some(event.sprite);
+            let is_hovered = self.pointer.hovered == Some(event.sprite);
+            let (is_button, was_pressed) = match self.ui.widgets[&id].kind {
+                WidgetKind::Button { state, .. } => (true, state == ButtonState::Pressed),
+                _ => (false, false),
+            };
+            match (is_button, event.kind) {
+                (true, PointerEventKind::Enter) => {
+                    let state = if is_pressed {
+                        ButtonState::Pressed
+                    } else {
+                        ButtonState::Hovered
+                    };
+                    self.set_button_state(id, state);
+                }
+                (true, PointerEventKind::Leave) => {
+                    self.set_button_state(id, ButtonState::Normal);
+                }
+                (true, PointerEventKind::Down) => {
+                    self.set_button_state(id, ButtonState::Pressed);
+                }
+                (true, PointerEventKind::Up) => {
+                    // only a press that started and ended on the button counts
+                    if was_pressed && is_hovered {
+                        self.ui.events.push(UiEvent::Clicked(id));
+                    }
+                    let state = if is_hovered {
+                        ButtonState::Hovered
+                    } else {
+                        ButtonState::Normal
+                    };
+                    self.set_button_state(id, state);
+                }
+                // only sliders are left that get events
+                (false, PointerEventKind::Down | PointerEventKind::Drag) => {
+                    self.set_slider_from(id, point);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// scrolls the innermost scroll view under the pointer, positive goes further down or right
+    pub(crate) fn pointer_wheel(&mut self, screen_x: f32, screen_y: f32, delta: f32) {
+        let nesting = |mut id: UiId| {
+            let mut count = 0;
+            while let Some(parent) = self.ui.widgets[&id].parent {
+                id = parent;
+                count += 1;
+            }
+            count
+        };
+        let target = self
+            .ui
+            .widgets
+            .iter()
+            .filter(|(_, widget)| {
+                matches!(widget.kind, WidgetKind::ScrollView { .. })
+                    && screen_x >= widget.pos.x
+                    && screen_y >= widget.pos.y
+                    && screen_x <= widget.pos.x + widget.extent.x
+                    && screen_y <= widget.pos.y + widget.extent.y
+            })
+            .max_by_key(|(id, _)| nesting(**id))
+            .map(|(id, _)| *id);
+        let id = match target {
+            Some(id) => id,
+            None => return,
+        };
+        let widget = self.ui.widgets.get_mut(&id).unwrap();
+        let view = main_axis(widget.extent, widget.direction);
+        if let WidgetKind::ScrollView {
+            scroll, content, ..
+        } = &mut widget.kind
+        {
+            let new = (*scroll + delta).clamp(0.0, (*content - view).max(0.0));
+            if new != *scroll {
+                *scroll = new;
+                self.ui.events.push(UiEvent::Scrolled(id, new));
+                self.ui.dirty = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::*;
+
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct ViewportId(u32);
+
+// the whole screen, for the main camera and render targets
+pub(crate) const FULL_VIEWPORT: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+/// another camera drawing the same scene onto part of the screen, for split screen or a minimap,
+/// drawn after the main camera in the order they were added
+pub(crate) struct Viewport {
+    pub(crate) camera: Camera,
+    // x, y, width, height as fractions of the screen from the top left
+    pub(crate) rect: [f32; 4],
+    pub(crate) layers: LayerMask,
+}
+
+impl Scene {
+    pub(crate) fn add_viewport(
+        &mut self,
+        camera: Camera,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    ) -> ViewportId {
+        let id = ViewportId(self.next_viewport_id);
+        self.next_viewport_id += 1;
+        self.viewports.insert(
+            id,
+            Viewport {
+                camera,
+                rect: [x, y, width, height],
+                layers: LayerMask::all(),
+            },
+        );
+        id
+    }
+
+    pub(crate) fn remove_viewport(&mut self, id: ViewportId) -> Result<(), &'static str> {
+        self.viewports.remove(&id).ok_or("invalid viewport id")?;
+        Ok(())
+    }
+
+    pub(crate) fn viewport_mut(&mut self, id: ViewportId) -> Result<&mut Viewport, &'static str> {
+        self.viewports.get_mut(&id).ok_or("invalid viewport id")
+    }
+
+    // which layers the camera passed to update sees, all of them by default
+    pub(crate) fn set_screen_layers(&mut self, layers: LayerMask) {
+        self.screen_layers = layers;
+    }
+
+    /// one pass per viewport after the main one, each culled on its own
+    pub(crate) fn emit_viewport_passes(&mut self) {
+        let mut ids: Vec<ViewportId> = self.viewports.keys().copied().collect();
+        ids.sort();
+        let screen = self.screen_extent();
+        for id in ids {
+            let viewport = &self.viewports[&id];
+            let (view, rect) = (viewport.camera.rect, viewport.rect);
+            let pixels = Vector2d {
+                x: screen.x * rect[2],
+                y: screen.y * rect[3],
+            };
+            self.emit_pass(&view, PassFilter::Screen(viewport.layers), pixels);
+            self.vert_attrib.end_pass(SCREEN_TARGET, 0, 0, &view, rect);
+        }
+    }
+}
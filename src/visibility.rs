@@ -0,0 +1,58 @@
+use crate::*;
+
+// what's hidden on top of the sprites' own flags, checked per sprite only against the groups it's
+// in, so toggling a whole layer or group is a single write
+pub(crate) struct Visibility {
+    hidden_layers: LayerMask,
+    pub(crate) hidden_groups: HashSet<GroupId>,
+}
+
+impl Visibility {
+    pub(crate) fn new() -> Self {
+        Self {
+            hidden_layers: LayerMask::none(),
+            hidden_groups: HashSet::new(),
+        }
+    }
+
+    pub(crate) fn shows_layer(&self, depth: f32) -> bool {
+        !self.hidden_layers.contains(depth)
+    }
+
+    pub(crate) fn shows(&self, data: &SpriteData) -> bool {
+        data.is_visible
+            && self.shows_layer(data.rect.top_left.2)
+            && (self.hidden_groups.is_empty()
+                || !data
+                    .groups
+                    .iter()
+                    .any(|group| self.hidden_groups.contains(group)))
+    }
+}
+
+impl Scene {
+    /// hides everything on the layer, sprites, text, tilemaps and emitters alike
+    pub(crate) fn set_layer_visible(&mut self, layer: u8, is_ui: bool, is_visible: bool) {
+        let hidden = self.visibility.hidden_layers;
+        self.visibility.hidden_layers = if is_visible {
+            hidden.without(layer, is_ui)
+        } else {
+            hidden.with(layer, is_ui)
+        };
+    }
+
+    // a sprite in a hidden group stays hidden whatever its other groups say
+    pub(crate) fn set_group_visible(
+        &mut self,
+        group: GroupId,
+        is_visible: bool,
+    ) -> Result<(), &'static str> {
+        self.group_members(group)?;
+        if is_visible {
+            self.visibility.hidden_groups.remove(&group);
+        } else {
+            self.visibility.hidden_groups.insert(group);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use crate::*;
+
+// just enough xml for tmx and bmfont files, no dtd, namespaces or cdata
+pub(crate) struct XmlElement {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<XmlElement>,
+    pub(crate) text: String,
+}
+
+impl XmlElement {
+    pub(crate) fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub(crate) fn attr_f32(&self, name: &str, default: f32) -> Result<f32, &'static str> {
+        match self.attr(name) {
+            Some(value) => value.parse().map_err(|_| "invalid number in xml"),
+            None => Ok(default),
+        }
+    }
+
+    pub(crate) fn attr_u32(&self, name: &str, default: u32) -> Result<u32, &'static str> {
+        match self.attr(name) {
+            Some(value) => value.parse().map_err(|_| "invalid number in xml"),
+            None => Ok(default),
+        }
+    }
+
+    pub(crate) fn attr_bool(&self, name: &str, default: bool) -> bool {
+        match self.attr(name) {
+            Some(value) => value != "0" && value != "false",
+            None => default,
+        }
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    pub(crate) fn children_named<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a XmlElement> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+}
+
+struct XmlParser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    // prolog, comments and doctype
+    fn skip_misc(&mut self) -> Result<(), &'static str> {
+        loop {
+            self.skip_whitespace();
+            let end = if self.rest().starts_with("<?") {
+                "?>"
+            } else if self.rest().starts_with("<!--") {
+                "-->"
+            } else if self.rest().starts_with("<!") {
+                ">"
+            } else {
+                return Ok(());
+            };
+            let index = self.rest().find(end).ok_or("unclosed xml declaration")?;
+            self.pos += index + end.len();
+        }
+    }
+
+    fn name(&mut self) -> Result<&'a str, &'static str> {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/' || c == '=')
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return Err("expected an xml name");
+        }
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn element(&mut self) -> Result<XmlElement, &'static str> {
+        if !self.rest().starts_with('<') {
+            return Err("expected an xml element");
+        }
+        self.pos += 1;
+        let mut element = XmlElement {
+            name: self.name()?.to_string(),
+            attributes: vec![],
+            children: vec![],
+            text: String::new(),
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.pos += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.pos += 1;
+                break;
+            }
+            let key = self.name()?.to_string();
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err("expected = after xml attribute");
+            }
+            self.pos += 1;
+            self.skip_whitespace();
+            let quote = self.rest().chars().next().ok_or("unexpected end of xml")?;
+            if quote != '"' && quote != '\'' {
+                return Err("xml attribute isn't quoted");
+            }
+            self.pos += 1;
+            let len = self.rest().find(quote).ok_or("unclosed xml attribute")?;
+            let value = unescape(&self.rest()[..len]);
+            self.pos += len + 1;
+            element.attributes.push((key, value));
+        }
+
+        loop {
+            let text_len = self.rest().find('<').ok_or("unclosed xml element")?;
+            element.text.push_str(&unescape(&self.rest()[..text_len]));
+            self.pos += text_len;
+            if self.rest().starts_with("</") {
+                self.pos += 2;
+                let name = self.name()?;
+                if name != element.name {
+                    return Err("mismatched xml closing tag");
+                }
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err("expected > after xml closing tag");
+                }
+                self.pos += 1;
+                return Ok(element);
+            }
+            if self.rest().starts_with("<!--") {
+                let index = self.rest().find("-->").ok_or("unclosed xml comment")?;
+                self.pos += index + 3;
+                continue;
+            }
+            element.children.push(self.element()?);
+        }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+pub(crate) fn parse_xml(src: &str) -> Result<XmlElement, &'static str> {
+    let mut parser = XmlParser { src, pos: 0 };
+    parser.skip_misc()?;
+    parser.element()
+}